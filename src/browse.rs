@@ -0,0 +1,248 @@
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::str::FromStr;
+
+use crate::workspace;
+
+/// Hosting service a remote belongs to, decides how its pages are found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Gitea,
+}
+
+impl FromStr for Forge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "github" => Ok(Forge::GitHub),
+            "gitlab" => Ok(Forge::GitLab),
+            "bitbucket" => Ok(Forge::Bitbucket),
+            "gitea" | "forgejo" => Ok(Forge::Gitea),
+            _ => Err(format!(
+                "Unknown host type {}, use github, gitlab, bitbucket or gitea",
+                s
+            )),
+        }
+    }
+}
+
+impl Forge {
+    /// Guesses the service from well known host names
+    fn detect(host: &str) -> Option<Forge> {
+        let host = host.to_lowercase();
+
+        match host.as_str() {
+            "github.com" => Some(Forge::GitHub),
+            "bitbucket.org" => Some(Forge::Bitbucket),
+            "codeberg.org" => Some(Forge::Gitea),
+            _ if host.contains("github") => Some(Forge::GitHub),
+            _ if host.contains("gitlab") => Some(Forge::GitLab),
+            _ if host.contains("bitbucket") => Some(Forge::Bitbucket),
+            _ if host.contains("gitea") || host.contains("forgejo") => Some(Forge::Gitea),
+            _ => None,
+        }
+    }
+
+    fn path(&self, page: &Page) -> String {
+        match (self, page) {
+            (_, Page::Home) => String::new(),
+            (Forge::GitLab, Page::PullRequests) => String::from("/-/merge_requests"),
+            (Forge::GitLab, Page::Issues) => String::from("/-/issues"),
+            (Forge::GitLab, Page::Branch(branch)) => format!("/-/tree/{}", branch),
+            (Forge::Bitbucket, Page::PullRequests) => String::from("/pull-requests"),
+            (Forge::Bitbucket, Page::Issues) => String::from("/issues"),
+            (Forge::Bitbucket, Page::Branch(branch)) => format!("/src/{}", branch),
+            (Forge::Gitea, Page::Branch(branch)) => format!("/src/branch/{}", branch),
+            (_, Page::PullRequests) => String::from("/pulls"),
+            (_, Page::Issues) => String::from("/issues"),
+            (_, Page::Branch(branch)) => format!("/tree/{}", branch),
+        }
+    }
+}
+
+/// Which page of a repository `ws browse` opens
+#[derive(Debug, Clone, PartialEq)]
+pub enum Page {
+    Home,
+    PullRequests,
+    Issues,
+    Branch(String),
+}
+
+/// How a host is browsed, from setting `host.<host>` like `gitlab` or
+/// `gitlab https://code.example.com` when the web pages live elsewhere
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostMapping {
+    pub forge: Forge,
+    /// Scheme and host of the web pages, defaults to `https://<host>`
+    pub web: Option<String>,
+}
+
+impl FromStr for HostMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let forge = parts.next().unwrap_or_default().parse()?;
+        let web = parts
+            .next()
+            .map(|web| web.trim_end_matches('/').to_string());
+
+        if let Some(web) = &web {
+            if !web.starts_with("http://") && !web.starts_with("https://") {
+                return Err(format!("{} is no http or https url", web));
+            }
+        }
+        if parts.next().is_some() {
+            return Err(String::from("Expected the host type and at most one url"));
+        }
+
+        Ok(HostMapping { forge, web })
+    }
+}
+
+/// Host and `owner/repo` path of a remote url, ports of ssh urls are dropped
+fn split_remote(url: &str) -> Option<(String, String)> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        // scp like syntax `git@host:owner/repo`
+        None => (None, url),
+    };
+    if scheme == Some("file") {
+        return None;
+    }
+
+    let rest = match rest.split_once('@') {
+        Some((user, rest)) if !user.contains('/') => rest,
+        _ => rest,
+    };
+
+    let (host, path) = match scheme {
+        Some(_) => rest.split_once('/')?,
+        None => rest.split_once(':')?,
+    };
+    let host = match (scheme, host.split_once(':')) {
+        (Some("http") | Some("https"), _) => host,
+        (_, Some((host, _port))) => host,
+        (_, None) => host,
+    };
+    let path = path.trim_start_matches('/');
+
+    match host.is_empty() || path.is_empty() {
+        true => None,
+        false => Some((host.to_string(), path.to_string())),
+    }
+}
+
+/// Web url of `page` of the repository behind `remote`. `mappings` are the
+/// configured hosts as `(host, mapping)`, hosts neither mapped nor known
+/// only have a home page
+pub fn url(
+    remote: &str,
+    page: &Page,
+    mappings: &[(String, HostMapping)],
+) -> Result<String, String> {
+    let (host, path) =
+        split_remote(remote).ok_or_else(|| format!("{} is not a hosted repository", remote))?;
+    let bare_host = host.split(':').next().unwrap_or(&host);
+
+    let mapping = mappings
+        .iter()
+        .find(|(mapped, _)| mapped.eq_ignore_ascii_case(bare_host))
+        .map(|(_, mapping)| mapping);
+    let forge = mapping
+        .map(|mapping| mapping.forge)
+        .or_else(|| Forge::detect(bare_host));
+    let web = mapping
+        .and_then(|mapping| mapping.web.clone())
+        .unwrap_or_else(|| format!("https://{}", host));
+
+    let suffix = match (forge, page) {
+        (Some(forge), page) => forge.path(page),
+        (None, Page::Home) => String::new(),
+        (None, _) => {
+            return Err(format!(
+                "Unknown host {}, map it with `ws config set host.{} github`",
+                bare_host, bare_host
+            ))
+        }
+    };
+
+    Ok(format!("{}/{}{}", web, path, suffix))
+}
+
+/// Opens `url` in `browser`, a command the url is appended to, or in the
+/// default browser of the system
+pub fn open(url: &str, browser: Option<&str>) -> io::Result<ExitStatus> {
+    let shell = workspace::command_shell();
+
+    match browser {
+        Some(browser) if cfg!(windows) => Command::new(shell)
+            .args(["-Command", &format!("{} \"{}\"", browser, url)])
+            .status(),
+        Some(browser) => Command::new(shell)
+            .args(["-c", &format!("{} \"$1\"", browser), "ws"])
+            .arg(url)
+            .status(),
+        None if cfg!(windows) => Command::new("cmd").args(["/C", "start", "", url]).status(),
+        None if cfg!(target_os = "macos") => Command::new("open").arg(url).status(),
+        None => Command::new("xdg-open").arg(url).status(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_web_urls_for_each_host() {
+        let branch = Page::Branch(String::from("feature/foo"));
+
+        assert_eq!(
+            url("git@github.com:Owner/repo.git", &Page::Home, &[]),
+            Ok(String::from("https://github.com/Owner/repo"))
+        );
+        assert_eq!(
+            url(
+                "https://gitlab.com/group/sub/repo.git",
+                &Page::PullRequests,
+                &[]
+            ),
+            Ok(String::from(
+                "https://gitlab.com/group/sub/repo/-/merge_requests"
+            ))
+        );
+        assert_eq!(
+            url("ssh://git@bitbucket.org:22/team/repo", &branch, &[]),
+            Ok(String::from(
+                "https://bitbucket.org/team/repo/src/feature/foo"
+            ))
+        );
+        assert_eq!(
+            url("https://codeberg.org/owner/repo", &Page::Issues, &[]),
+            Ok(String::from("https://codeberg.org/owner/repo/issues"))
+        );
+
+        assert!(url("git@git.corp.com:team/api", &Page::Issues, &[]).is_err());
+        let mappings = [(
+            String::from("git.corp.com"),
+            "gitea https://code.corp.com/".parse().unwrap(),
+        )];
+        assert_eq!(
+            url("git@git.corp.com:team/api", &branch, &mappings),
+            Ok(String::from(
+                "https://code.corp.com/team/api/src/branch/feature/foo"
+            ))
+        );
+
+        assert!(url("file:///srv/repo.git", &Page::Home, &[]).is_err());
+        assert!("gitlab ftp://x".parse::<HostMapping>().is_err());
+    }
+}
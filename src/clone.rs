@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::workspace::{Dir, Workspace};
+
+/// Remote and branch a missing directory can be cloned from
+pub fn source(dir: &Dir) -> Option<(&str, Option<&str>)> {
+    let git = dir.git.as_ref()?;
+    let remote = git.remote.as_deref()?;
+
+    Some((remote, git.default_branch.as_deref()))
+}
+
+/// Where every directory of `ws` belongs below `root`: at the same location
+/// relative to the directory all of them share. Without `root` every
+/// directory stays where it is
+pub fn targets<'a>(ws: &'a Workspace, root: Option<&Path>) -> Vec<(&'a Dir, PathBuf)> {
+    let Some(root) = root else {
+        return ws.dir_iter().map(|dir| (dir, dir.path.clone())).collect();
+    };
+
+    let paths: Vec<&Path> = ws.dir_iter().map(|dir| dir.path.as_path()).collect();
+    let shared = shared_parent(&paths);
+
+    ws.dir_iter()
+        .map(|dir| {
+            let relative = dir.path.strip_prefix(&shared).unwrap_or(&dir.path);
+            (dir, root.join(relative))
+        })
+        .collect()
+}
+
+/// Deepest directory containing all `paths`, for a single path its parent
+fn shared_parent(paths: &[&Path]) -> PathBuf {
+    let mut shared = match paths.first() {
+        Some(first) => first.parent().unwrap_or(first).to_path_buf(),
+        None => return PathBuf::new(),
+    };
+
+    for path in &paths[1..] {
+        while !path.starts_with(&shared) || *path == shared {
+            if !shared.pop() {
+                return shared;
+            }
+        }
+    }
+
+    shared
+}
+
+/// `git clone` of `remote` into `path`, checking out `branch` when known
+pub fn clone_command(remote: &str, branch: Option<&str>, path: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.args(["clone", "--quiet"]);
+    if let Some(branch) = branch {
+        command.args(["--branch", branch]);
+    }
+    // a remote starting with `-` is no option
+    command.arg("--").arg(remote).arg(path);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::init_repo;
+    use crate::git::{self, GitIdentity};
+
+    #[test]
+    fn targets_keep_the_relative_locations() {
+        let mut ws = Workspace::new(String::from("x"));
+        ws.add_dir(Dir::new("/home/alice/code/api"));
+        ws.add_dir(Dir::new("/home/alice/code/group/web"));
+
+        let paths: Vec<PathBuf> = targets(&ws, Some(Path::new("/work")))
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/work/api"), PathBuf::from("/work/group/web")]
+        );
+
+        let mut single = Workspace::new(String::from("y"));
+        single.add_dir(Dir::new("/home/alice/code/api"));
+        assert_eq!(
+            targets(&single, Some(Path::new("/work")))[0].1,
+            PathBuf::from("/work/api")
+        );
+        assert_eq!(
+            targets(&single, None)[0].1,
+            PathBuf::from("/home/alice/code/api")
+        );
+    }
+
+    #[test]
+    fn clones_the_recorded_remote_and_branch() {
+        let root = tempfile::tempdir().unwrap();
+        let origin = root.path().join("origin");
+        init_repo(&origin);
+        git::test_support::run_git(&origin, &["branch", "develop"]);
+
+        let mut dir = Dir::new(root.path().join("clone"));
+        dir.git = Some(GitIdentity {
+            remote: Some(format!("file://{}", origin.display())),
+            default_branch: Some(String::from("develop")),
+            ..Default::default()
+        });
+
+        let (remote, branch) = source(&dir).unwrap();
+        let status = clone_command(remote, branch, &dir.path).status().unwrap();
+
+        assert!(status.success());
+        let identity = git::identity(&dir.path).unwrap();
+        assert_eq!(identity.default_branch.as_deref(), Some("main"));
+        assert_eq!(
+            git::status(&dir.path).unwrap().branch.as_deref(),
+            Some("develop")
+        );
+        assert!(source(&Dir::new("/srv/plain")).is_none());
+    }
+}
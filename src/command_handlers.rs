@@ -1,25 +1,56 @@
+use crate::browse::{self, HostMapping, Page};
+use crate::clone;
+use crate::commits::{self, Filter};
+use crate::db::WorkspaceWrite;
+use crate::doctor::{self, MissingRepo, Severity};
+use crate::document::{self, Action, Document, Format, Strategy};
+use crate::edit;
+#[allow(unused_imports)]
 use crate::errors::{self, Error};
-use crate::utils;
+use crate::git;
+use crate::manifest::{self, Diff};
+use crate::multi::{self, Job, Outcome, Output};
+use crate::project::{self, ProjectFile};
+use crate::roots;
+use crate::scan;
+use crate::services::{self, Service, Stopped};
+use crate::settings;
+use crate::snapshot::{self, Snapshot};
+use crate::sources::{self, Source};
+use crate::status;
+use crate::tasks::{self, Origin, Task};
+use crate::utils::{self, PathOptions};
+use crate::watch;
 use crate::workspace::{Dir, Workspace};
-use crate::{db, workspace};
+use crate::{db, vscode, workspace};
 use colored::Colorize;
 use inquire::list_option::ListOption;
 use inquire::ui::{IndexPrefix, RenderConfig};
-use inquire::Select;
+use inquire::{Confirm, MultiSelect, Select};
 use prettytable::Table;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 /// Open a workspace
-/// opens all the directories in a code editor
-pub fn open_workspace(name: String) -> Result<(), Error> {
+/// opens all the directories in a code editor, or only directory `dir`
+pub fn open_workspace(name: String, dir: Option<String>) -> Result<(), Error> {
     let ws = db::fetch_workspace_with_dirs_by_name(&name);
 
-    if let Some(space) = ws {
+    if let Some(mut space) = ws {
+        if let Some(dir) = dir {
+            let id = find_dir(&space, &dir)?.id;
+            space.retain_dirs(|d| d.id == id);
+        }
+        clone_missing_dirs(&space);
+        run_init_scripts(&space)?;
         workspace::open_workspace(space);
     } else {
         eprintln!("Workspace not found");
-        return Err(Error::DbError(String::from("Not Found")));
+        return Err(Error::NotFound(format!("Workspace {}", name)));
     }
 
     Ok(())
@@ -38,7 +69,11 @@ pub fn print_workspaces() -> Result<(), Error> {
         let mut dir_table = table!();
 
         space.dir_iter().for_each(|dir| {
-            dir_table.add_row(row![format!("{}", dir.path)]);
+            dir_table.add_row(row![
+                dir.label.clone().unwrap_or_default(),
+                format!("{}", dir.path.display()),
+                if dir.primary { "primary" } else { "" }
+            ]);
         });
 
         table.add_row(row![format!("{}", space.name), dir_table]);
@@ -55,11 +90,25 @@ pub fn update_editor(name: String) -> Result<(), Error> {
     Ok(())
 }
 
+/// Override the editor of workspace `w_name`
+pub fn update_workspace_editor(w_name: String, editor: String) -> Result<(), Error> {
+    if let Err(err) = db::update_workspace_editor(&w_name, Some(editor)) {
+        eprintln!(
+            "{}",
+            format!("Cannot find workspace with name {}", w_name).red()
+        );
+        return Err(Error::DbError(err.to_string()));
+    }
+
+    Ok(())
+}
+
 /// Delete a workspace
 pub fn delete_workspace(w_name: String) {
     db::delete_workspace(w_name).expect("Error deleting workspace");
 }
 
+#[allow(dead_code, unused_variables)]
 pub fn set_init_script(
     w_name: String,
     dir: String,
@@ -71,18 +120,40 @@ pub fn set_init_script(
 }
 
 /// Add a new workspace
-pub fn add_workspace(w_name: Option<String>, path: Option<PathBuf>) -> Result<usize, Error> {
+#[allow(clippy::unnecessary_unwrap)]
+pub fn add_workspace(
+    w_name: Option<String>,
+    path: Option<PathBuf>,
+    path_options: PathOptions,
+) -> Result<usize, Error> {
     let path = path.unwrap_or(PathBuf::from("."));
 
     println!("Path: {}", path.display());
-    let canonical = fs::canonicalize(path).unwrap();
+    let canonical = resolve_dir(&path, path_options)?;
 
     println!("Canonical Path: {}", canonical.display());
 
-    // get the current directory name
-    let dir_name = canonical.to_str().unwrap().split("/").last().unwrap();
+    let project = project::read(&canonical).inspect_err(|err| {
+        eprintln!("{}", err.to_string().red());
+    })?;
+    let project_name = project.and_then(|(_, project)| project.workspace);
 
-    let w_name = w_name.unwrap_or(dir_name.to_string());
+    // the name from the project file, or the current directory name
+    let w_name = match (w_name, project_name, canonical.file_name()) {
+        (Some(w_name), _, _) | (None, Some(w_name), _) => w_name,
+        (None, None, Some(dir_name)) => dir_name.to_string_lossy().into_owned(),
+        (None, None, None) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "Cannot name a workspace after {}, use --name",
+                    canonical.display()
+                )
+                .red()
+            );
+            return Err(Error::InvalidPath(String::from("No workspace name")));
+        }
+    };
 
     // check if the workspace already exists
     let already = db::fetch_workspace_with_dirs_by_name(&w_name);
@@ -90,135 +161,2973 @@ pub fn add_workspace(w_name: Option<String>, path: Option<PathBuf>) -> Result<us
     if already.is_some() {
         // add the directory to the workspace
         let ws = already.unwrap();
-        if let Some(canonical_str) = canonical.to_str() {
-            let dir_exists = ws.check_dir_already_exists(canonical_str);
-            if dir_exists.is_some() {
-                eprintln!(
-                    "{}",
-                    format!(
-                        "Directory {} already exists in workspace {}",
-                        canonical.to_str().unwrap(),
-                        ws.name
-                    )
-                    .red()
-                );
-                return Err(Error::DbError(String::from("Already Exists")));
-            } else {
-                // add the directory to the workspace
-                let _ = db::insert_new_dir_for_workspace(ws.get_id(), String::from(canonical_str));
+        insert_dir(&ws, &canonical)?;
+        println!(
+            "{}",
+            format!(
+                "Directory {} added to workspace {}",
+                canonical.display(),
+                ws.name
+            )
+            .green()
+        );
+        apply_projects(&w_name, &canonical)?;
+        return Ok(ws.get_id() as usize);
+    }
+
+    let id = match db::insert_new_workspace(Workspace::new(w_name.clone())) {
+        Ok(id) => match db::insert_new_dir_for_workspace(id as i32, &canonical) {
+            Ok(dir_id) => {
+                if let Some(identity) = git::identity(&canonical) {
+                    db::update_dir_git_identity(dir_id as i32, &identity)?;
+                }
+                Ok(id)
+            }
+            Err(err) => {
+                eprintln!("Error {:?}", err);
+                Err(Error::DbError(String::from(
+                    "Cannot Insert dir into database",
+                )))
+            }
+        },
+        Err(err) => {
+            eprintln!("Error {:?}", err);
+            Err(Error::from(err))
+        }
+    }?;
+
+    apply_projects(&w_name, &canonical)?;
+
+    Ok(id)
+}
+
+/// Apply the project files of directory `start` and of its siblings,
+/// siblings are added to workspace `w_name` when missing
+fn apply_projects(w_name: &str, start: &Path) -> Result<(), Error> {
+    let mut queue = vec![start.to_path_buf()];
+    let mut seen: Vec<PathBuf> = vec![];
+
+    while let Some(path) = queue.pop() {
+        if seen.contains(&path) {
+            continue;
+        }
+        seen.push(path.clone());
+
+        let mut ws = fetch_workspace(w_name)?;
+        if ws.check_dir_already_exists(&path).is_none() {
+            if !path.is_dir() {
                 println!(
                     "{}",
-                    format!(
-                        "Directory {} added to workspace {}",
-                        canonical.to_str().unwrap(),
-                        ws.name
-                    )
-                    .green()
+                    format!("Sibling {} does not exist", path.display()).yellow()
                 );
-                return Ok(ws.get_id() as usize);
+                continue;
             }
+            insert_dir(&ws, &path)?;
+            println!(
+                "{}",
+                format!("Sibling {} added to workspace {}", path.display(), w_name).green()
+            );
+            ws = fetch_workspace(w_name)?;
+        }
+
+        let index = ws.check_dir_already_exists(&path).unwrap();
+        let dir = ws.dir_iter().nth(index).unwrap();
+
+        if let Some(project) = sync_project(dir)? {
+            queue.extend(
+                project
+                    .sibling_paths(&path)
+                    .into_iter()
+                    .map(|sibling| fs::canonicalize(&sibling).unwrap_or(sibling)),
+            );
         }
-        // check if the directory already exists in the workspace
     }
 
-    match db::insert_new_workspace(Workspace::new(w_name)) {
-        Ok(id) => {
-            match db::insert_new_dir_for_workspace(
-                id as i32,
-                canonical.to_str().unwrap().to_string(),
-            ) {
-                Ok(_) => {
-                    return Ok(id);
-                }
-                Err(err) => {
-                    eprintln!("Error {:?}", err);
-                    return Err(Error::DbError(String::from(
-                        "Cannot Insert dir into database",
-                    )));
+    Ok(())
+}
+
+/// Apply the project file of `dir` when it changed since it was last applied,
+/// returns the current project file
+fn sync_project(dir: &Dir) -> Result<Option<ProjectFile>, Error> {
+    let current = project::read(&dir.path).inspect_err(|err| {
+        eprintln!("{}: {}", dir.path.display(), err.to_string().red());
+    })?;
+
+    let text = current.as_ref().map(|(text, _)| text.as_str());
+    if text == dir.project.as_deref() {
+        return Ok(current.map(|(_, project)| project));
+    }
+
+    let old = dir
+        .project
+        .as_deref()
+        .and_then(|text| ProjectFile::parse(text).ok());
+    db::apply_project(
+        dir.id,
+        old.as_ref(),
+        current
+            .as_ref()
+            .map(|(text, project)| (text.as_str(), project)),
+    )?;
+
+    match &current {
+        Some((_, project)) => {
+            println!(
+                "{}",
+                format!("Applied {}", dir.path.join(project::FILE_NAME).display()).green()
+            );
+            if let Some(scripts) = project.scripts() {
+                if dir.trusted_scripts.as_ref() != Some(&scripts) {
+                    ask_trust(dir, &scripts)?;
                 }
-            };
+            }
         }
-        Err(err) => {
-            eprintln!("Error {:?}", err);
-            return Err(Error::DbError(String::from("Cannot Insert database")));
+        None => println!(
+            "{}",
+            format!("Removed {} of {}", project::FILE_NAME, dir.path.display()).yellow()
+        ),
+    }
+
+    Ok(current.map(|(_, project)| project))
+}
+
+/// Ask if `scripts` of the project file of `dir` may run, remembering a yes
+fn ask_trust(dir: &Dir, scripts: &str) -> Result<bool, Error> {
+    println!("{} runs:", dir.path.join(project::FILE_NAME).display());
+    for line in scripts.lines() {
+        println!("    {}", line);
+    }
+
+    let trusted = Confirm::new("Trust these scripts?")
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false);
+
+    if trusted {
+        db::trust_scripts(dir.id, scripts)?;
+    }
+
+    Ok(trusted)
+}
+
+/// Check that the scripts of `dir` may run, asking when they come
+/// from a project file which changed since the user trusted it
+fn scripts_trusted(dir: &Dir) -> Result<bool, Error> {
+    let scripts = dir
+        .project
+        .as_deref()
+        .and_then(|text| ProjectFile::parse(text).ok())
+        .and_then(|project| project.scripts());
+
+    match scripts {
+        Some(scripts) if dir.trusted_scripts.as_ref() != Some(&scripts) => ask_trust(dir, &scripts),
+        _ => Ok(true),
+    }
+}
+
+/// Run the init scripts of the directories of `ws` one after another
+fn run_init_scripts(ws: &Workspace) -> Result<(), Error> {
+    for dir in ws.dir_iter() {
+        let script = match &dir.init {
+            Some(script) => script,
+            None => continue,
+        };
+
+        if !scripts_trusted(dir)? {
+            println!(
+                "{}",
+                format!("Skipping untrusted init script of {}", dir.display_name()).yellow()
+            );
+            continue;
+        }
+
+        match workspace::run_script(dir, script, &ws.env) {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!(
+                "{}",
+                format!("Init script of {} failed: {}", dir.display_name(), status).yellow()
+            ),
+            Err(err) => println!(
+                "{}",
+                format!("Cannot run init script of {}: {}", dir.display_name(), err).yellow()
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-apply the project files which changed, of all workspaces or only `w_name`
+pub fn sync_projects(w_name: Option<String>) -> Result<(), Error> {
+    let spaces = match &w_name {
+        Some(w_name) => vec![fetch_workspace(w_name)?],
+        None => db::fetch_all_workspaces_with_dirs()?,
+    };
+
+    let mut failed = 0;
+    for space in &spaces {
+        for dir in space.dir_iter().filter(|dir| dir.path.is_dir()) {
+            if apply_projects(&space.name, &dir.path).is_err() {
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!(
+            "{}",
+            format!("{} project files could not be applied", failed).red()
+        );
+        return Err(Error::InvalidFile(String::from("Invalid project files")));
+    }
+
+    println!("{}", "Project files are up to date".green());
+
+    Ok(())
+}
+
+/// Resolve `path` given on the command line to the directory to store
+fn resolve_dir(path: &Path, path_options: PathOptions) -> Result<PathBuf, Error> {
+    utils::resolve_path(path, path_options).inspect_err(|err| {
+        eprintln!("{}", err.to_string().red());
+    })
+}
+
+/// Insert directory `path` into workspace `ws`,
+/// a directory can only be part of a workspace once
+fn insert_dir(ws: &Workspace, path: &Path) -> Result<usize, Error> {
+    let inserted = db::insert_new_dir_for_workspace(ws.get_id(), path).map_err(Error::from);
+
+    if let (Ok(id), Some(identity)) = (&inserted, git::identity(path)) {
+        db::update_dir_git_identity(*id as i32, &identity)?;
+    }
+
+    match inserted {
+        Err(Error::AlreadyExists(_)) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "Directory {} already exists in workspace {}",
+                    path.display(),
+                    ws.name
+                )
+                .red()
+            );
+            Err(Error::AlreadyExists(format!(
+                "Directory {} in workspace {}",
+                path.display(),
+                ws.name
+            )))
+        }
+        res => res,
+    }
+}
+
+/// Fails unless workspace `w_name` exists
+fn ensure_workspace_exists(w_name: &str) -> Result<(), Error> {
+    match db::workspace_exists(w_name) {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            eprintln!(
+                "{}",
+                format!("Cannot find workspace with name {}", w_name).red()
+            );
+            Err(Error::NotFound(format!("Workspace {}", w_name)))
+        }
+        Err(err) => Err(Error::from(err)),
+    }
+}
+
+/// Fails if workspace `w_name` already exists
+fn ensure_workspace_is_new(w_name: &str) -> Result<(), Error> {
+    match db::workspace_exists(w_name) {
+        Ok(false) => Ok(()),
+        Ok(true) => {
+            eprintln!("{}", format!("Workspace {} already exists", w_name).red());
+            Err(Error::AlreadyExists(format!("Workspace {}", w_name)))
+        }
+        Err(err) => Err(Error::from(err)),
+    }
+}
+
+/// Rename workspace `old` to `new`
+pub fn rename_workspace(old: String, new: String) -> Result<(), Error> {
+    ensure_workspace_exists(&old)?;
+    ensure_workspace_is_new(&new)?;
+
+    db::rename_workspace(&old, &new)?;
+
+    println!(
+        "{}",
+        format!("Workspace {} renamed to {}", old, new).green()
+    );
+
+    Ok(())
+}
+
+/// Copy workspace `src` with all its directories into new workspace `dst`
+pub fn copy_workspace(src: String, dst: String) -> Result<(), Error> {
+    ensure_workspace_exists(&src)?;
+    ensure_workspace_is_new(&dst)?;
+
+    db::copy_workspace(&src, &dst)?;
+
+    println!("{}", format!("Workspace {} copied to {}", src, dst).green());
+
+    Ok(())
+}
+
+/// Merge workspaces `sources` into workspace `into`
+/// removing duplicate directories
+pub fn merge_workspaces(sources: Vec<String>, into: String) -> Result<(), Error> {
+    for source in &sources {
+        ensure_workspace_exists(source)?;
+    }
+
+    let count = db::merge_workspaces(&sources, &into)?;
+
+    println!(
+        "{}",
+        format!(
+            "Merged {} into {} ({} directories)",
+            sources.join(", "),
+            into,
+            count
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Move directories `dirs` of workspace `w_name` into workspace `into`
+pub fn split_workspace(w_name: String, dirs: Vec<String>, into: String) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    let mut dir_ids = vec![];
+    for dir in &dirs {
+        let id = find_dir(&ws, dir)?.id;
+        if !dir_ids.contains(&id) {
+            dir_ids.push(id);
+        }
+    }
+
+    if dir_ids.len() == ws.dir_iter().count() {
+        eprintln!(
+            "{}",
+            format!(
+                "Splitting every directory out of {} would leave it empty, use rename instead",
+                w_name
+            )
+            .red()
+        );
+        return Err(Error::DbError(String::from("Empty workspace")));
+    }
+
+    db::split_workspace(&w_name, &dir_ids, &into)?;
+
+    println!(
+        "{}",
+        format!(
+            "Moved {} directories from {} to {}",
+            dir_ids.len(),
+            w_name,
+            into
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Fetch workspace `w_name` with its directories
+fn fetch_workspace(w_name: &str) -> Result<Workspace, Error> {
+    match db::fetch_workspace_with_dirs_by_name(w_name) {
+        Some(ws) => Ok(ws),
+        None => {
+            eprintln!(
+                "{}",
+                format!("Cannot find workspace with name {}", w_name).red()
+            );
+            Err(Error::NotFound(format!("Workspace {}", w_name)))
+        }
+    }
+}
+
+/// Workspace `w_name`, all workspaces with `all`, or else the workspaces
+/// containing the current directory
+fn select_workspaces(w_name: Option<String>, all: bool) -> Result<Vec<Workspace>, Error> {
+    if let Some(w_name) = w_name {
+        return Ok(vec![fetch_workspace(&w_name)?]);
+    }
+
+    let spaces = db::fetch_all_workspaces_with_dirs()?;
+    if all {
+        return Ok(spaces);
+    }
+
+    let current = env::current_dir()?;
+    let current = fs::canonicalize(&current).unwrap_or(current);
+    let containing: Vec<Workspace> = spaces
+        .into_iter()
+        .filter(|ws| ws.dir_iter().any(|dir| current.starts_with(&dir.path)))
+        .collect();
+
+    if containing.is_empty() {
+        eprintln!(
+            "{}",
+            "The current directory is in no workspace, use -w or --all".red()
+        );
+        return Err(Error::NotFound(String::from(
+            "Workspace of the current directory",
+        )));
+    }
+
+    Ok(containing)
+}
+
+/// Find a directory of `ws` by its label or path
+fn find_dir<'a>(ws: &'a Workspace, dir: &str) -> Result<&'a Dir, Error> {
+    let found = ws.find_dir(dir).or_else(|| {
+        let path = utils::expand_path(OsStr::new(dir)).ok()?;
+        let path = fs::canonicalize(&path).unwrap_or(utils::normalize(&path));
+        ws.check_dir_already_exists(path)
+            .and_then(|i| ws.dir_iter().nth(i))
+    });
+
+    match found {
+        Some(found) => Ok(found),
+        None => {
+            eprintln!(
+                "{}",
+                format!("Directory {} is not part of workspace {}", dir, ws.name).red()
+            );
+            Err(Error::NotFound(format!("Directory {}", dir)))
+        }
+    }
+}
+
+/// Check that `label` can name directory `dir_id` of `ws`
+fn validate_label(ws: &Workspace, label: &str, dir_id: i32) -> Result<(), Error> {
+    let problem = if label.is_empty() || label.contains('/') {
+        Some(format!("Invalid label {}", label))
+    } else if ws
+        .dir_iter()
+        .any(|d| d.id != dir_id && d.label.as_deref() == Some(label))
+    {
+        Some(format!(
+            "Label {} is already used in workspace {}",
+            label, ws.name
+        ))
+    } else {
+        None
+    };
+
+    match problem {
+        Some(problem) => {
+            eprintln!("{}", problem.red());
+            Err(Error::DbError(String::from("Invalid label")))
         }
+        None => Ok(()),
     }
 }
 
 /// Add a directory to a workspace
-pub fn add_dir_to_workspace(w_name: String, path: PathBuf) -> Result<(), Error> {
+pub fn add_dir_to_workspace(
+    w_name: String,
+    path: PathBuf,
+    label: Option<String>,
+    path_options: PathOptions,
+) -> Result<(), Error> {
     if let Some(workspace) = db::fetch_workspace_with_dirs_by_name(&w_name) {
-        let canonical = utils::get_canonical_path(path);
-        if let Ok(_) = db::insert_new_dir_for_workspace(workspace.get_id(), canonical) {
-            ()
-        } else {
-            return Err(Error::DbError(String::from("Error inserting directory")));
+        if let Some(label) = &label {
+            validate_label(&workspace, label, 0)?;
+        }
+        let canonical = resolve_dir(&path, path_options)?;
+        let id = insert_dir(&workspace, &canonical)?;
+        if label.is_some() {
+            db::update_dir_label(id as i32, label)?;
         }
     } else {
         eprintln!("Cannot find workspace with name {}", w_name);
-        return Err(Error::DbError(String::from("Not found")));
+        return Err(Error::NotFound(format!("Workspace {}", w_name)));
     }
 
     Ok(())
 }
 
-/// Remove a directory from a workspace :`w_name`
-pub fn remove_dir_from_workspace(w_name: String) -> Result<(), Error> {
-    if let Some(ws) = db::fetch_workspace_with_dirs_by_name(&w_name) {
-        // store a reference of all the directories in the workspace
-        let dirs: Vec<&Dir> = ws.dir_iter().collect();
+/// Set or remove (`label` is `None`) the label of directory `dir` of workspace `w_name`
+pub fn label_dir(w_name: String, dir: String, label: Option<String>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let dir = find_dir(&ws, &dir)?;
 
-        // map the directories to `inquire` options
-        let options = ws
+    if let Some(label) = &label {
+        validate_label(&ws, label, dir.id)?;
+    }
+
+    db::update_dir_label(dir.id, label.clone())?;
+
+    match label {
+        Some(label) => println!(
+            "{}",
+            format!("{} labeled {}", dir.path.display(), label).green()
+        ),
+        None => println!(
+            "{}",
+            format!("Label removed from {}", dir.path.display()).green()
+        ),
+    }
+
+    Ok(())
+}
+
+/// Make directory `dir` the primary directory of workspace `w_name`
+pub fn set_primary_dir(w_name: String, dir: String) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let dir = find_dir(&ws, &dir)?;
+
+    db::set_primary_dir(ws.get_id(), dir.id)?;
+
+    println!(
+        "{}",
+        format!(
+            "{} is now the primary directory of {}",
+            dir.display_name(),
+            w_name
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Move directories `dirs` to the front of workspace `w_name` in the given order,
+/// the remaining directories keep their order after them
+pub fn reorder_dirs(w_name: String, dirs: Vec<String>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    let mut ordered: Vec<i32> = vec![];
+    for dir in &dirs {
+        let id = find_dir(&ws, dir)?.id;
+        if !ordered.contains(&id) {
+            ordered.push(id);
+        }
+    }
+    let rest: Vec<i32> = ws
+        .dir_iter()
+        .map(|d| d.id)
+        .filter(|id| !ordered.contains(id))
+        .collect();
+    ordered.extend(rest);
+
+    db::reorder_dirs(ws.get_id(), &ordered)?;
+
+    for id in ordered {
+        if let Some(dir) = ws.dir_iter().find(|d| d.id == id) {
+            println!("{}", dir.display_name());
+        }
+    }
+
+    Ok(())
+}
+
+/// Start an interactive shell in directory `dir` of workspace `w_name`,
+/// the primary directory when `dir` is `None`
+pub fn open_shell(w_name: String, dir: Option<String>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    let dir = match &dir {
+        Some(dir) => find_dir(&ws, dir)?,
+        None => ws.primary_dir().unwrap(),
+    };
+
+    workspace::open_shell(dir).map_err(|err| Error::DbError(err.to_string()))
+}
+
+/// Rewrite every directory below `old` to be below `new`
+/// for all workspaces or only workspace `w_name`
+pub fn relocate_dirs(
+    old: PathBuf,
+    new: PathBuf,
+    w_name: Option<String>,
+    dry_run: bool,
+) -> Result<(), Error> {
+    // the old location is usually gone already, so it is never canonicalized
+    let old = resolve_dir(
+        &old,
+        PathOptions {
+            allow_missing: true,
+            keep_symlinks: true,
+            ..Default::default()
+        },
+    )?;
+    let new = resolve_dir(
+        &new,
+        PathOptions {
+            allow_missing: true,
+            ..Default::default()
+        },
+    )?;
+
+    let spaces = match &w_name {
+        Some(w_name) => vec![fetch_workspace(w_name)?],
+        None => db::fetch_all_workspaces_with_dirs()?,
+    };
+
+    let mut changes: Vec<(i32, PathBuf)> = vec![];
+    let mut missing = 0;
+
+    for space in &spaces {
+        let moved: Vec<(&Dir, PathBuf)> = space
             .dir_iter()
-            .enumerate()
-            .map(|(i, dir)| ListOption::new(i as usize, &dir.path))
+            .filter_map(|dir| Some((dir, utils::replace_prefix(&dir.path, &old, &new)?)))
             .collect();
 
-        let mut render_config = RenderConfig::default_colored();
-        render_config.option_index_prefix = IndexPrefix::Simple;
+        if moved.is_empty() {
+            continue;
+        }
 
-        let ans = Select::new("Select the directory to delete", options)
-            .with_render_config(render_config)
-            .prompt();
+        println!("{}", space.name.bold());
+        for (dir, path) in moved {
+            println!("{}", format!("- {}", dir.path.display()).red());
+            println!("{}", format!("+ {}", path.display()).green());
+            if !path.exists() {
+                println!(
+                    "{}",
+                    format!("  warning: {} does not exist", path.display()).yellow()
+                );
+                missing += 1;
+            }
+            changes.push((dir.id, path));
+        }
+    }
 
-        if let Ok(ans) = ans {
-            let index = ans.index;
+    if changes.is_empty() {
+        println!(
+            "{}",
+            format!("No directories below {}", old.display()).yellow()
+        );
+        return Ok(());
+    }
 
-            let dir = dirs[index];
+    if missing > 0 {
+        println!(
+            "{}",
+            format!("{} directories would still not exist", missing).yellow()
+        );
+    }
 
-            return match db::remove_dir_from_workspace(dir.id) {
-                Ok(_) => {
-                    println!("{}", "Directory deleted".green());
-                    Ok(())
-                }
-                Err(er) => {
-                    println!("{}", "Error while removing directoy".red());
-                    Err(Error::DbError(er.to_string()))
-                }
-            };
-        } else {
-            println!("{}", "No directory selected".yellow());
+    if dry_run {
+        println!("{}", "Dry run, nothing changed".yellow());
+        return Ok(());
+    }
+
+    match db::update_dir_paths(&changes).map_err(Error::from) {
+        Ok(()) => {
+            println!(
+                "{}",
+                format!("Relocated {} directories", changes.len()).green()
+            );
+            Ok(())
         }
+        Err(err @ Error::AlreadyExists(_)) => {
+            eprintln!(
+                "{}",
+                "A relocated directory is already part of the same workspace, nothing changed"
+                    .red()
+            );
+            Err(err)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Write workspaces `w_names`, or all workspaces, as a document to `output` or stdout
+pub fn export_document(
+    w_names: Vec<String>,
+    format: Format,
+    output: Option<PathBuf>,
+) -> Result<(), Error> {
+    let mut spaces = db::fetch_all_workspaces_with_dirs()?;
+
+    if !w_names.is_empty() {
+        for w_name in &w_names {
+            ensure_workspace_exists(w_name)?;
+        }
+        spaces.retain(|ws| w_names.contains(&ws.name));
+    }
+
+    let roots = db::fetch_roots()?;
+    let text = Document::new(&spaces, &roots, dirs::home_dir().as_deref()).to_string(format);
+
+    match output {
+        Some(output) => {
+            fs::write(&output, text)?;
+            println!(
+                "{}",
+                format!(
+                    "{} workspaces exported to {}",
+                    spaces.len(),
+                    output.display()
+                )
+                .green()
+            );
+        }
+        None => print!("{}", text),
     }
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::db;
+/// Import the workspaces of document `file`, resolving name conflicts with `strategy`
+/// With `dry_run` only the changes which would be made are shown
+pub fn import_document(
+    file: PathBuf,
+    format: Option<Format>,
+    strategy: Strategy,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let format = format.unwrap_or(Format::of_file(&file));
+    let roots = db::fetch_roots()?;
 
-    #[test]
-    fn test_list_workspaces() {
-        db::initialize_db().expect("Error Initializing databsae");
+    let imported = fs::read_to_string(&file)
+        .map_err(Error::from)
+        .and_then(|text| Document::parse(&text, format))
+        .and_then(|document| document.into_workspaces(&roots, dirs::home_dir().as_deref()))
+        .inspect_err(|err| {
+            eprintln!("{}", err.to_string().red());
+        })?;
+
+    let existing = db::fetch_all_workspaces_with_dirs()?;
+    let mut changes = document::plan_import(imported, &existing, strategy);
+
+    for change in &changes {
+        let (action, name) = (&change.action, &change.workspace.name);
+        let line = match action {
+            Action::Create => format!("create    {} ({})", name, change.detail).green(),
+            Action::Overwrite => format!("overwrite {} ({})", name, change.detail).yellow(),
+            Action::Merge => format!("merge     {} ({})", name, change.detail).yellow(),
+            Action::Conflict => format!("conflict  {} ({})", name, change.detail).red(),
+            Action::Unchanged => format!("unchanged {}", name).normal(),
+        };
+        println!("{}", line);
     }
 
-    #[test]
-    fn test_open_workspace() {
+    if dry_run {
+        return Ok(());
+    }
+
+    for change in changes.iter_mut() {
+        // missing directories keep the remote given in the document
+        for dir in change.workspace.dirs_mut() {
+            if let Some(identity) = git::identity(&dir.path) {
+                dir.git = Some(identity);
+            }
+        }
+    }
+
+    let writes: Vec<WorkspaceWrite> = changes
+        .iter()
+        .filter_map(|change| match change.action {
+            Action::Create => Some(WorkspaceWrite::Create(&change.workspace)),
+            Action::Overwrite => Some(WorkspaceWrite::Replace(&change.workspace)),
+            Action::Merge => Some(WorkspaceWrite::Merge(&change.workspace)),
+            Action::Conflict | Action::Unchanged => None,
+        })
+        .collect();
+
+    db::write_workspaces(&writes)?;
+
+    println!(
+        "{}",
+        format!("{} workspaces imported", writes.len()).green()
+    );
+
+    Ok(())
+}
+
+/// Read the workspaces declared in manifest `file`
+fn load_manifest(file: &Path) -> Result<Vec<Workspace>, Error> {
+    let roots = db::fetch_roots()?;
+
+    fs::read_to_string(file)
+        .map_err(Error::from)
+        .and_then(|text| Document::parse(&text, Format::of_file(file)))
+        .and_then(|document| document.into_workspaces(&roots, dirs::home_dir().as_deref()))
+        .inspect_err(|err| {
+            eprintln!("{}", err.to_string().red());
+        })
+}
+
+/// Print the changes `diffs` would make and the workspaces left alone
+fn print_diffs(diffs: &[Diff], undeclared: &[&str], prune: bool) {
+    for diff in diffs {
+        match diff {
+            Diff::Create(ws) => {
+                println!("{}", format!("+ create {}", ws.name).green());
+                for dir in ws.dir_iter() {
+                    println!("    + dir {}", dir.path.display());
+                }
+            }
+            Diff::Update { desired, changes } => {
+                println!("{}", format!("~ update {}", desired.name).yellow());
+                for change in changes {
+                    println!("    {}", change);
+                }
+            }
+            Diff::Delete(ws) => println!("{}", format!("- delete {}", ws.name).red()),
+        }
+    }
+
+    if !prune && !undeclared.is_empty() {
+        println!(
+            "Not declared, kept without --prune: {}",
+            undeclared.join(", ")
+        );
+    }
+}
+
+/// Show what `apply` would change to make the database match manifest `file`
+pub fn plan_manifest(file: PathBuf, prune: bool) -> Result<(), Error> {
+    let desired = load_manifest(&file)?;
+    let current = db::fetch_all_workspaces_with_dirs()?;
+
+    let diffs = manifest::diff(&desired, &current, prune);
+    print_diffs(&diffs, &manifest::undeclared(&desired, &current), prune);
+
+    if diffs.is_empty() {
+        println!(
+            "{}",
+            "No changes, the workspaces match the manifest".green()
+        );
+    }
+
+    Ok(())
+}
+
+/// Make the database match manifest `file`, with `prune` undeclared
+/// workspaces are deleted after asking unless `yes`
+pub fn apply_manifest(file: PathBuf, prune: bool, yes: bool) -> Result<(), Error> {
+    let mut desired = load_manifest(&file)?;
+    let current = db::fetch_all_workspaces_with_dirs()?;
+
+    for dir in desired.iter_mut().flat_map(|ws| ws.dirs_mut()) {
+        if let Some(identity) = git::identity(&dir.path) {
+            dir.git = Some(identity);
+        }
+    }
+
+    let diffs = manifest::diff(&desired, &current, prune);
+    print_diffs(&diffs, &manifest::undeclared(&desired, &current), prune);
+
+    if diffs.is_empty() {
+        println!(
+            "{}",
+            "No changes, the workspaces match the manifest".green()
+        );
+        return Ok(());
+    }
+
+    let deletes = diffs
+        .iter()
+        .filter(|d| matches!(d, Diff::Delete(_)))
+        .count();
+    if deletes > 0 && !yes {
+        let confirmed = Confirm::new(&format!("Delete {} workspaces?", deletes))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        if !confirmed {
+            println!("Nothing applied");
+            return Ok(());
+        }
+    }
+
+    let writes: Vec<WorkspaceWrite> = diffs.iter().map(Diff::to_write).collect();
+    db::write_workspaces(&writes)?;
+
+    println!("{}", format!("{} workspaces changed", writes.len()).green());
+
+    Ok(())
+}
+
+/// Edit workspace `w_name` as TOML in `$EDITOR` and apply the changes,
+/// an invalid file is reopened with the errors on top
+pub fn edit_workspace(w_name: String) -> Result<(), Error> {
+    let current = fetch_workspace(&w_name)?;
+    let roots = db::fetch_roots()?;
+    let home = dirs::home_dir();
+
+    // only readable by the user as the environment may hold secrets, removed
+    // again when dropped
+    let file = tempfile::Builder::new()
+        .prefix("ws-edit-")
+        .suffix(".toml")
+        .tempfile()?;
+    let mut text = edit::to_text(&current, &roots, home.as_deref());
+    let mut last_invalid: Option<String> = None;
+
+    let desired = loop {
+        fs::write(file.path(), &text)?;
+        let status = workspace::open_in_editor(file.path())?;
+        let edited = edit::strip_comments(&fs::read_to_string(file.path())?);
+
+        if !status.success() || edited.trim().is_empty() || last_invalid.as_ref() == Some(&edited) {
+            println!("Edit cancelled, nothing changed");
+            return Ok(());
+        }
+
+        match edit::validate(&edited, &w_name, &roots, home.as_deref()) {
+            Ok(desired) => break desired,
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}", error.red());
+                }
+                text = edit::with_errors(&edited, &errors);
+                last_invalid = Some(edited);
+            }
+        }
+    };
+    drop(file);
+
+    let desired = [desired];
+    let diffs = manifest::diff(&desired, std::slice::from_ref(&current), false);
+    print_diffs(&diffs, &[], false);
+
+    if diffs.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+
+    let writes: Vec<WorkspaceWrite> = diffs.iter().map(Diff::to_write).collect();
+    db::write_workspaces(&writes)?;
+
+    println!("{}", format!("Workspace {} updated", w_name).green());
+
+    Ok(())
+}
+
+/// Create workspace `w_name` from the folders of VS Code workspace `file`,
+/// named after the file unless given
+pub fn import_vscode(file: PathBuf, w_name: Option<String>) -> Result<(), Error> {
+    let w_name = match w_name {
+        Some(w_name) => w_name,
+        None => match file.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => {
+                eprintln!("{}", "Cannot name the workspace, use --name".red());
+                return Err(Error::InvalidPath(String::from("No workspace name")));
+            }
+        },
+    };
+    ensure_workspace_is_new(&w_name)?;
+
+    let folders = vscode::read_folders(&file).inspect_err(|err| {
+        eprintln!("{}", err.to_string().red());
+    })?;
+
+    let mut ws = Workspace::new(w_name);
+    for (path, name) in folders {
+        let path = resolve_dir(
+            &path,
+            PathOptions {
+                allow_missing: true,
+                ..Default::default()
+            },
+        )?;
+        if !path.exists() {
+            println!("{}", format!("{} does not exist", path.display()).yellow());
+        }
+
+        let mut dir = Dir::new(path);
+        dir.label = name.filter(|name| !name.is_empty() && !name.contains('/'));
+        dir.git = git::identity(&dir.path);
+        ws.add_dir(dir);
+    }
+
+    db::create_workspace(&ws)?;
+
+    println!(
+        "{}",
+        format!(
+            "Workspace {} imported with {} directories",
+            ws.name,
+            ws.dir_iter().count()
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Offer the directories listed by another tool as a checklist,
+/// every checked directory becomes a workspace of its own
+pub fn import_projects(source: Source, file: Option<PathBuf>) -> Result<(), Error> {
+    let listed = sources::read(source, file.as_deref()).inspect_err(|err| {
+        eprintln!("{}", err.to_string().red());
+    })?;
+
+    let spaces = db::fetch_all_workspaces_with_dirs()?;
+    let mut candidates: Vec<PathBuf> = vec![];
+    let (mut missing, mut known) = (0, 0);
+
+    for path in listed {
+        let path = match utils::resolve_path(&path, PathOptions::default()) {
+            Ok(path) if path.is_dir() => path,
+            _ => {
+                missing += 1;
+                continue;
+            }
+        };
+
+        if spaces
+            .iter()
+            .any(|ws| ws.check_dir_already_exists(&path).is_some())
+        {
+            known += 1;
+        } else if !candidates.contains(&path) {
+            candidates.push(path);
+        }
+    }
+
+    if missing + known > 0 {
+        println!(
+            "Skipping {} directories which no longer exist and {} already in a workspace",
+            missing, known
+        );
+    }
+    if candidates.is_empty() {
+        println!("Nothing new to import from {}", source.name());
+        return Ok(());
+    }
+
+    let checked = choose_dirs(
+        "Select the directories to turn into workspaces",
+        &candidates,
+        false,
+    );
+    if checked.is_empty() {
+        println!("Nothing imported");
+        return Ok(());
+    }
+
+    add_workspaces(&checked, None)?;
+    println!("{}", "Directories imported".green());
+
+    Ok(())
+}
+
+/// Find the project roots below `root` and add them after confirmation,
+/// each as a workspace of its own or all to workspace `w_name`
+pub fn scan(
+    root: PathBuf,
+    depth: Option<usize>,
+    markers: Vec<String>,
+    w_name: Option<String>,
+    yes: bool,
+) -> Result<(), Error> {
+    let root = resolve_dir(&root, PathOptions::default())?;
+    let markers = match markers.is_empty() {
+        true => vec![String::from(scan::DEFAULT_MARKER)],
+        false => markers,
+    };
+
+    let spaces = db::fetch_all_workspaces_with_dirs()?;
+    let known = |path: &Path| {
+        spaces
+            .iter()
+            .filter(|ws| w_name.as_ref().is_none_or(|w_name| &ws.name == w_name))
+            .any(|ws| ws.check_dir_already_exists(path).is_some())
+    };
+
+    let found = scan::find_projects(&root, depth, &markers);
+    let total = found.len();
+    let projects: Vec<PathBuf> = found.into_iter().filter(|path| !known(path)).collect();
+
+    if total > projects.len() {
+        println!(
+            "Skipping {} projects already in a workspace",
+            total - projects.len()
+        );
+    }
+    if projects.is_empty() {
+        println!("No new projects found below {}", root.display());
+        return Ok(());
+    }
+
+    let projects = match yes {
+        true => projects,
+        false => choose_dirs("Select the projects to add", &projects, true),
+    };
+    if projects.is_empty() {
+        println!("Nothing added");
+        return Ok(());
+    }
+
+    add_workspaces(&projects, w_name.as_deref())?;
+    println!("{}", format!("{} projects added", projects.len()).green());
+
+    Ok(())
+}
+
+/// Checklist of `paths`, returns the checked ones or nothing when cancelled
+fn choose_dirs(message: &str, paths: &[PathBuf], checked: bool) -> Vec<PathBuf> {
+    let options: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    let all: Vec<usize> = (0..paths.len()).collect();
+    let defaults: &[usize] = if checked { &all } else { &[] };
+
+    MultiSelect::new(message, options)
+        .with_default(defaults)
+        .with_page_size(15)
+        .raw_prompt()
+        .map(|answer| answer.iter().map(|o| paths[o.index].clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Add every directory of `paths` to workspace `into`, or as a workspace
+/// named after it, directories naming their workspace in a project file join it
+fn add_workspaces(paths: &[PathBuf], into: Option<&str>) -> Result<(), Error> {
+    let mut taken: Vec<String> = db::fetch_all_workspaces()?
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+
+    for path in paths {
+        let declared = matches!(project::read(path), Ok(Some((_, p))) if p.workspace.is_some());
+        let w_name = match (into, declared, path.file_name()) {
+            (Some(into), _, _) => Some(String::from(into)),
+            (None, false, Some(dir_name)) => {
+                let dir_name = dir_name.to_string_lossy().into_owned();
+                let w_name = (1..)
+                    .map(|i| match i {
+                        1 => dir_name.clone(),
+                        i => format!("{}-{}", dir_name, i),
+                    })
+                    .find(|name| !taken.contains(name))
+                    .unwrap();
+                taken.push(w_name.clone());
+                Some(w_name)
+            }
+            _ => None,
+        };
+
+        add_workspace(w_name, Some(path.clone()), PathOptions::default())?;
+    }
+
+    Ok(())
+}
+
+/// Show branch, upstream and working state of every directory
+pub fn print_status(
+    w_name: Option<String>,
+    all: bool,
+    format: status::Format,
+) -> Result<(), Error> {
+    let spaces = select_workspaces(w_name, all)?;
+    let statuses = status::collect(&spaces);
+
+    if format == status::Format::Json {
+        // plain strings, numbers and paths always serialize
+        println!("{}", serde_json::to_string_pretty(&statuses).unwrap());
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "Workspace",
+        "Directory",
+        "Branch",
+        "Upstream",
+        "Changed",
+        "Untracked",
+        "Stashes",
+        "State"
+    ]);
+
+    for dir in statuses {
+        let Some(git) = dir.git else {
+            let state = match dir.exists {
+                true => "not a repository".normal(),
+                false => "missing".red(),
+            };
+            table.add_row(row![dir.workspace, dir.dir, "", "", "", "", "", state]);
+            continue;
+        };
+
+        let upstream = match (git.ahead, git.behind) {
+            (Some(0), Some(0)) => String::from("up to date"),
+            (Some(ahead), Some(behind)) => format!("+{} -{}", ahead, behind),
+            _ => String::from("none"),
+        };
+        let count = |n: u32| match n {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        let state = match (git.operation, git.is_dirty()) {
+            (Some(operation), _) => format!("{} in progress", operation).red(),
+            (None, true) => "dirty".yellow(),
+            (None, false) => "clean".green(),
+        };
+
+        table.add_row(row![
+            dir.workspace,
+            dir.dir,
+            git.branch.as_deref().unwrap_or("(detached)"),
+            upstream,
+            count(git.changed),
+            count(git.untracked),
+            count(git.stashes),
+            state
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Print the commits of every repository of the selected workspaces as one
+/// stream, or with `summary` grouped by day and directory
+pub fn print_log(
+    w_name: Option<String>,
+    all: bool,
+    filter: Filter,
+    format: commits::Format,
+    summary: bool,
+) -> Result<(), Error> {
+    let spaces = select_workspaces(w_name, all)?;
+    let (found, errors) = commits::collect(&spaces, &filter);
+
+    for (dir, err) in errors {
+        eprintln!(
+            "{}",
+            format!("Cannot read the log of {}: {}", dir, err).yellow()
+        );
+    }
+
+    if found.is_empty() {
+        eprintln!("{}", "No commits found".yellow());
+        return Ok(());
+    }
+
+    match summary {
+        true => print!("{}", commits::render_summary(&found, format)),
+        false => print!("{}", commits::render(&found, format)),
+    }
+
+    Ok(())
+}
+
+/// Open the web page of every repository of workspace `w_name`, or only of
+/// directory `dir`: its pull requests, issues or current branch when asked.
+/// With `print` the urls are printed instead
+pub fn browse(
+    w_name: String,
+    dir: Option<String>,
+    pull_requests: bool,
+    issues: bool,
+    branch: bool,
+    print: bool,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let dirs: Vec<&Dir> = match &dir {
+        Some(dir) => vec![find_dir(&ws, dir)?],
+        None => ws.dir_iter().collect(),
+    };
+
+    let mappings: Vec<(String, HostMapping)> = db::fetch_settings()?
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let host = settings::host_of(&name)?.to_string();
+            Some((host, value.parse().ok()?))
+        })
+        .collect();
+
+    let mut urls = vec![];
+    for dir in dirs {
+        let name = dir.short_name();
+        let remote = git::identity(&dir.path)
+            .or_else(|| dir.git.clone())
+            .and_then(|git| git.remote);
+        let Some(remote) = remote else {
+            eprintln!("{}", format!("{} has no remote", name).yellow());
+            continue;
+        };
+
+        let page = match (pull_requests, issues, branch) {
+            (true, _, _) => Page::PullRequests,
+            (_, true, _) => Page::Issues,
+            (_, _, true) => match git::status(&dir.path).and_then(|status| status.branch) {
+                Some(branch) => Page::Branch(branch),
+                None => {
+                    eprintln!("{}", format!("{} is on no branch", name).yellow());
+                    continue;
+                }
+            },
+            _ => Page::Home,
+        };
+
+        match browse::url(&remote, &page, &mappings) {
+            Ok(url) => urls.push(url),
+            Err(err) => eprintln!("{}", format!("{}: {}", name, err).yellow()),
+        }
+    }
+
+    if urls.is_empty() {
+        eprintln!("{}", "Nothing to open".red());
+        return Err(Error::InvalidPath(String::from("No remote")));
+    }
+
+    if print {
+        urls.iter().for_each(|url| println!("{}", url));
+        return Ok(());
+    }
+
+    let browser = db::get_setting(settings::BROWSER)?;
+    for url in urls {
+        println!("Opening {}", url);
+        let status = browse::open(&url, browser.as_deref())?;
+        if !status.success() {
+            eprintln!("{}", format!("Cannot open {}", url).red());
+            return Err(Error::CommandFailed(format!("Cannot open {}", url)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Run git with `args` in every repository of the selected workspaces,
+/// creating a branch is refused while any repository has changes
+pub fn git_command(
+    w_name: Option<String>,
+    all: bool,
+    jobs: Option<usize>,
+    continue_on_error: bool,
+    args: Vec<String>,
+) -> Result<(), Error> {
+    let spaces = select_workspaces(w_name, all)?;
+    let several = spaces.len() > 1;
+
+    let mut repos = vec![];
+    let mut skipped = vec![];
+    for ws in &spaces {
+        for dir in ws.dir_iter() {
+            let name = match several {
+                true => format!("{}/{}", ws.name, dir.short_name()),
+                false => dir.short_name().into_owned(),
+            };
+            let job = Job {
+                name,
+                dir: dir.path.clone(),
+            };
+            match git::is_repo(&dir.path) {
+                true => repos.push(job),
+                false => skipped.push(job),
+            }
+        }
+    }
+
+    if git::creates_branch(&args) {
+        let statuses = utils::parallel_map(&repos, utils::default_threads(), |job| {
+            git::status(&job.dir).is_some_and(|status| status.is_dirty())
+        });
+        let dirty: Vec<&str> = repos
+            .iter()
+            .zip(statuses)
+            .filter(|(_, dirty)| *dirty)
+            .map(|(job, _)| job.name.as_str())
+            .collect();
+
+        if !dirty.is_empty() {
+            eprintln!(
+                "{}",
+                format!(
+                    "Not creating a branch, these repositories have changes: {}",
+                    dirty.join(", ")
+                )
+                .red()
+            );
+            return Err(Error::CommandFailed(String::from("Dirty repositories")));
+        }
+    }
+
+    let threads = jobs.unwrap_or_else(utils::default_threads);
+    let outcomes = multi::run_all(
+        &repos,
+        threads,
+        continue_on_error,
+        Output::Prefixed,
+        |_, job| {
+            let mut command = Command::new("git");
+            command.args(&args).current_dir(&job.dir);
+            command
+        },
+    );
+
+    let mut table = outcome_table(&repos, &outcomes);
+    for job in &skipped {
+        table.add_row(row![job.name, "skipped, not a repository".yellow(), ""]);
+    }
+    println!();
+    table.printstd();
+
+    let failed = outcomes.iter().filter(|(o, _)| !o.is_success()).count();
+    if failed > 0 {
+        eprintln!(
+            "{}",
+            format!(
+                "git {} failed in {} of {} repositories",
+                args.join(" "),
+                failed,
+                repos.len()
+            )
+            .red()
+        );
+        return Err(Error::CommandFailed(format!(
+            "{} repositories failed",
+            failed
+        )));
+    }
+
+    Ok(())
+}
+
+/// Run `command` in the directories of workspace `w_name`, or only in `dirs`,
+/// with the environment of the workspace. A single argument is run as a
+/// shell script, several as the program and its arguments
+pub fn exec_command(
+    w_name: String,
+    dirs: Vec<String>,
+    parallel: Option<usize>,
+    fail_fast: bool,
+    group: bool,
+    command: Vec<String>,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    let selected: Vec<&Dir> = match dirs.is_empty() {
+        true => ws.dir_iter().collect(),
+        false => dirs
+            .iter()
+            .map(
+                |name| match ws.dir_iter().find(|dir| dir.short_name() == *name) {
+                    Some(dir) => Ok(dir),
+                    None => find_dir(&ws, name),
+                },
+            )
+            .collect::<Result<_, _>>()?,
+    };
+    let jobs: Vec<Job> = selected
+        .iter()
+        .map(|dir| Job {
+            name: dir.short_name().into_owned(),
+            dir: dir.path.clone(),
+        })
+        .collect();
+
+    let output = match group {
+        true => Output::Grouped,
+        false => Output::Prefixed,
+    };
+    let outcomes = multi::run_all(
+        &jobs,
+        parallel.unwrap_or(1),
+        !fail_fast,
+        output,
+        |_, job| dir_command(&command, &job.dir, &ws.env),
+    );
+
+    println!();
+    outcome_table(&jobs, &outcomes).printstd();
+
+    let failed = outcomes.iter().filter(|(o, _)| !o.is_success()).count();
+    if failed > 0 {
+        eprintln!(
+            "{}",
+            format!(
+                "{} failed in {} of {} directories",
+                command.join(" "),
+                failed,
+                jobs.len()
+            )
+            .red()
+        );
+        return Err(Error::CommandFailed(format!(
+            "{} directories failed",
+            failed
+        )));
+    }
+
+    Ok(())
+}
+
+/// `command` of `ws exec` or `ws watch` in `dir`, a single argument is run
+/// as a shell script
+fn dir_command(command: &[String], dir: &Path, env: &BTreeMap<String, String>) -> Command {
+    match command {
+        [script] => workspace::script_command(dir, script, env),
+        [program, args @ ..] => {
+            let mut process = Command::new(program);
+            process.args(args).current_dir(dir).envs(env);
+            process
+        }
+        [] => unreachable!("the command is required"),
+    }
+}
+
+/// Watch every directory of workspace `w_name` and run `command` in the
+/// directories in which files changed, or with `all` in every directory
+pub fn watch(w_name: String, all: bool, debounce: u64, command: Vec<String>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    let jobs: Vec<Job> = ws
+        .dir_iter()
+        .filter(|dir| dir.path.is_dir())
+        .map(|dir| Job {
+            name: dir.short_name().into_owned(),
+            dir: dir.path.clone(),
+        })
+        .collect();
+    if jobs.is_empty() {
+        eprintln!(
+            "{}",
+            format!("Workspace {} has no directory to watch", w_name).red()
+        );
+        return Err(Error::InvalidPath(String::from("No directory to watch")));
+    }
+
+    let roots: Vec<PathBuf> = jobs.iter().map(|job| job.dir.clone()).collect();
+    println!(
+        "{}",
+        format!(
+            "Watching {} directories of {}, press Ctrl-C to stop",
+            roots.len(),
+            w_name
+        )
+        .green()
+    );
+
+    let result = watch::watch(
+        &roots,
+        std::time::Duration::from_millis(debounce),
+        |changed| {
+            let run: Vec<Job> = jobs
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| all || changed.contains(i))
+                .map(|(_, job)| job.clone())
+                .collect();
+            let names: Vec<&str> = changed.iter().map(|&i| jobs[i].name.as_str()).collect();
+            println!(
+                "\n{}",
+                format!("── changed in {} ──", names.join(", ")).bold()
+            );
+
+            let outcomes = multi::run_all(&run, run.len(), true, Output::Prefixed, |_, job| {
+                dir_command(&command, &job.dir, &ws.env)
+            });
+            for (job, (outcome, time)) in run.iter().zip(&outcomes) {
+                let result = format!(
+                    "{} {} ({:.1}s)",
+                    job.name,
+                    outcome.describe(),
+                    time.as_secs_f64()
+                );
+                match outcome.is_success() {
+                    true => println!("{}", result.green()),
+                    false => println!("{}", result.red()),
+                }
+            }
+        },
+    );
+
+    result.map_err(|err| {
+        eprintln!("{}", err.red());
+        Error::CommandFailed(err)
+    })
+}
+
+/// Run task `name` of workspace `w_name` in every directory having it, the
+/// tasks it depends on first. With `dry_run` the steps are only listed
+pub fn run_task(w_name: String, name: String, dry_run: bool) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let all = db::fetch_tasks(ws.get_id())?;
+
+    let steps = match tasks::plan(&ws, &all, &name) {
+        Ok(steps) => steps,
+        Err(err) => {
+            eprintln!("{}", err.red());
+            return Err(Error::InvalidFile(err));
+        }
+    };
+
+    if dry_run {
+        for (i, step) in steps.iter().enumerate() {
+            println!("{}. {} {}", i + 1, step.name().cyan(), step.task.command);
+        }
+        return Ok(());
+    }
+
+    let mut checked: Vec<i32> = vec![];
+    for step in steps
+        .iter()
+        .filter(|step| step.task.origin == Origin::Project)
+    {
+        if checked.contains(&step.dir.id) {
+            continue;
+        }
+        checked.push(step.dir.id);
+
+        if !scripts_trusted(step.dir)? {
+            let message = format!(
+                "Not running the untrusted tasks of {}",
+                step.dir.display_name()
+            );
+            eprintln!("{}", message.red());
+            return Err(Error::CommandFailed(message));
+        }
+    }
+
+    let jobs: Vec<Job> = steps
+        .iter()
+        .map(|step| Job {
+            name: step.name(),
+            dir: step.dir.path.clone(),
+        })
+        .collect();
+
+    // one after another in the planned order, stopping at the first failure
+    let outcomes = multi::run_all(&jobs, 1, false, Output::Prefixed, |index, job| {
+        workspace::script_command(&job.dir, &steps[index].task.command, &ws.env)
+    });
+
+    println!();
+    outcome_table(&jobs, &outcomes).printstd();
+
+    if let Some(failed) = jobs
+        .iter()
+        .zip(&outcomes)
+        .find(|(_, (outcome, _))| !outcome.is_success() && *outcome != Outcome::NotRun)
+    {
+        let message = format!("Task {} failed in {}", name, failed.0.name);
+        eprintln!("{}", message.red());
+        return Err(Error::CommandFailed(message));
+    }
+
+    Ok(())
+}
+
+/// Print the tasks of workspace `w_name`
+pub fn print_tasks(w_name: String) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let all = db::fetch_tasks(ws.get_id())?;
+
+    if all.is_empty() {
+        println!(
+            "{}",
+            "No tasks, add them with `ws tasks add` or in a .ws.toml".yellow()
+        );
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["Task", "Directory", "Command", "Depends on", "From"]);
+
+    for task in &all {
+        let dir = match task.dir_id {
+            Some(id) => ws
+                .dir_iter()
+                .find(|dir| dir.id == id)
+                .map_or(String::new(), |dir| dir.short_name().into_owned()),
+            None => String::from("every directory"),
+        };
+        let origin = match task.origin {
+            Origin::Project => project::FILE_NAME,
+            Origin::Workspace => "workspace",
+        };
+
+        table.add_row(row![
+            task.name,
+            dir,
+            task.command,
+            task.depends.join(", "),
+            origin
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Add task `name` running `command` to workspace `w_name`, in directory
+/// `dir` or in every directory
+pub fn add_task(
+    w_name: String,
+    name: String,
+    dir: Option<String>,
+    depends: Vec<String>,
+    command: Vec<String>,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    if name.is_empty() || name.contains([':', ',']) {
+        let message = format!("Invalid task name {}, it may not contain : or ,", name);
+        eprintln!("{}", message.red());
+        return Err(Error::InvalidFile(message));
+    }
+
+    let dir_id = match &dir {
+        Some(dir) => Some(find_dir(&ws, dir)?.id),
+        None => None,
+    };
+
+    db::set_task(
+        ws.get_id(),
+        &Task {
+            name: name.clone(),
+            dir_id,
+            command: workspace::script_of(&command),
+            depends,
+            origin: Origin::Workspace,
+        },
+    )?;
+    println!(
+        "{}",
+        format!("Task {} added to workspace {}", name, w_name).green()
+    );
+
+    Ok(())
+}
+
+/// Remove task `name` from workspace `w_name`
+pub fn remove_task(w_name: String, name: String) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    if !db::remove_task(ws.get_id(), &name)? {
+        eprintln!(
+            "{}",
+            format!("Workspace {} has no task {} to remove", w_name, name).red()
+        );
+        return Err(Error::NotFound(format!("Task {}", name)));
+    }
+
+    println!("{}", format!("Task {} removed", name).green());
+
+    Ok(())
+}
+
+/// Services of `ws` named in `names`, all of them when none are named
+fn select_services(ws: &Workspace, names: &[String]) -> Result<Vec<Service>, Error> {
+    let all = db::fetch_services(ws.get_id())?;
+    if names.is_empty() {
+        return Ok(all);
+    }
+
+    names
+        .iter()
+        .map(
+            |name| match all.iter().find(|service| service.name == *name) {
+                Some(service) => Ok(service.clone()),
+                None => {
+                    eprintln!(
+                        "{}",
+                        format!("Workspace {} has no service {}", ws.name, name).red()
+                    );
+                    Err(Error::NotFound(format!("Service {}", name)))
+                }
+            },
+        )
+        .collect()
+}
+
+/// Directory `service` runs in
+fn service_dir<'a>(ws: &'a Workspace, service: &Service) -> Option<&'a Dir> {
+    match service.dir_id {
+        Some(id) => ws.dir_iter().find(|dir| dir.id == id),
+        None => ws.primary_dir(),
+    }
+}
+
+/// Start the services of workspace `w_name` which are not running, or only
+/// the ones in `names`
+pub fn services_up(w_name: String, names: Vec<String>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let selected = select_services(&ws, &names)?;
+
+    if selected.is_empty() {
+        println!(
+            "{}",
+            format!("Workspace {} has no services, see `ws services`", w_name).yellow()
+        );
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for service in &selected {
+        let log = services::log_file(&ws, &service.name)?;
+        if service.is_running(&log) {
+            println!("{} is already running", service.name);
+            continue;
+        }
+
+        let Some(dir) = service_dir(&ws, service).filter(|dir| dir.path.is_dir()) else {
+            eprintln!(
+                "{}",
+                format!("Not starting {}, its directory is missing", service.name).red()
+            );
+            failed += 1;
+            continue;
+        };
+
+        match services::start(service, &dir.path, &ws.env, &log) {
+            Ok(pid) => {
+                db::set_service_pid(ws.get_id(), &service.name, Some(pid))?;
+                println!(
+                    "{}",
+                    format!(
+                        "Started {} in {} (pid {}), logs in {}",
+                        service.name,
+                        dir.short_name(),
+                        pid,
+                        log.display()
+                    )
+                    .green()
+                );
+            }
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    format!("Cannot start {}: {}", service.name, err).red()
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::CommandFailed(format!(
+            "{} services not started",
+            failed
+        )));
+    }
+
+    Ok(())
+}
+
+/// Stop the running services of workspace `w_name`, or only the ones in
+/// `names`, killing those still running after `timeout` seconds
+pub fn services_down(w_name: String, names: Vec<String>, timeout: u64) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let started: Vec<Service> = select_services(&ws, &names)?
+        .into_iter()
+        .filter(|service| service.pid.is_some())
+        .collect();
+
+    if started.is_empty() {
+        println!("{}", "No services running".yellow());
+        return Ok(());
+    }
+
+    for service in &started {
+        let pid = service.pid.unwrap();
+        let log = services::log_file(&ws, &service.name)?;
+        // a stale pid, e.g. from before a reboot, is cleared without a signal
+        match services::stop(pid, &log, std::time::Duration::from_secs(timeout))? {
+            Stopped::Terminated => println!("{}", format!("Stopped {}", service.name).green()),
+            Stopped::Killed => println!(
+                "{}",
+                format!("Killed {}, still running after {}s", service.name, timeout).yellow()
+            ),
+            Stopped::NotRunning => println!("{} had already exited", service.name),
+        }
+        db::set_service_pid(ws.get_id(), &service.name, None)?;
+    }
+
+    Ok(())
+}
+
+/// Show the services of workspace `w_name` and whether they run, without a
+/// workspace the started services of all workspaces
+pub fn print_processes(w_name: Option<String>) -> Result<(), Error> {
+    let spaces = match &w_name {
+        Some(w_name) => vec![fetch_workspace(w_name)?],
+        None => db::fetch_all_workspaces_with_dirs()?,
+    };
+    let rows: Vec<(String, Service)> = match &w_name {
+        Some(w_name) => db::fetch_services(spaces[0].get_id())?
+            .into_iter()
+            .map(|service| (w_name.clone(), service))
+            .collect(),
+        None => db::fetch_started_services()?,
+    };
+
+    if rows.is_empty() {
+        println!("{}", "No services running".yellow());
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "Workspace",
+        "Service",
+        "State",
+        "PID",
+        "Started (UTC)",
+        "Command"
+    ]);
+
+    for (workspace, service) in rows {
+        let running = match spaces.iter().find(|ws| ws.name == workspace) {
+            Some(ws) => service.is_running(&services::log_file(ws, &service.name)?),
+            None => false,
+        };
+        let state = match (service.pid, running) {
+            (Some(_), true) => "running".green(),
+            (Some(_), false) => "exited, see `ws logs`".red(),
+            (None, _) => "stopped".normal(),
+        };
+        table.add_row(row![
+            workspace,
+            service.name,
+            state,
+            service.pid.map_or(String::new(), |pid| pid.to_string()),
+            service.started.unwrap_or_default(),
+            service.command
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Print the last `lines` lines of the log of service `name`, with `follow`
+/// keep printing its output
+pub fn service_logs(w_name: String, name: String, lines: usize, follow: bool) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    select_services(&ws, std::slice::from_ref(&name))?;
+
+    let log = services::log_file(&ws, &name)?;
+    if !log.is_file() {
+        eprintln!(
+            "{}",
+            format!("{} has not been started yet, start it with `ws up`", name).red()
+        );
+        return Err(Error::InvalidPath(format!("No log of {}", name)));
+    }
+
+    services::print_log(&log, lines, follow)?;
+
+    Ok(())
+}
+
+/// Print the services defined for workspace `w_name`
+pub fn print_services(w_name: String) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let all = db::fetch_services(ws.get_id())?;
+
+    if all.is_empty() {
+        println!(
+            "{}",
+            "No services, add them with `ws services add` or `ws services import`".yellow()
+        );
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["Service", "Directory", "Command", "Restart"]);
+    for service in &all {
+        let dir =
+            service_dir(&ws, service).map_or(String::new(), |dir| dir.short_name().into_owned());
+        let restart = match service.restart {
+            true => "on failure",
+            false => "",
+        };
+        table.add_row(row![service.name, dir, service.command, restart]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Add service `name` running `command` to workspace `w_name`, in directory
+/// `dir` or in the primary directory
+pub fn add_service(
+    w_name: String,
+    name: String,
+    dir: Option<String>,
+    restart: bool,
+    command: Vec<String>,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    if !services::is_valid_name(&name) {
+        let message = format!(
+            "Invalid service name {}, use letters, digits, - and _",
+            name
+        );
+        eprintln!("{}", message.red());
+        return Err(Error::InvalidFile(message));
+    }
+
+    let dir_id = match &dir {
+        Some(dir) => Some(find_dir(&ws, dir)?.id),
+        None => None,
+    };
+
+    db::set_service(
+        ws.get_id(),
+        &Service {
+            name: name.clone(),
+            dir_id,
+            command: workspace::script_of(&command),
+            restart,
+            pid: None,
+            started: None,
+        },
+    )?;
+    println!(
+        "{}",
+        format!("Service {} added to workspace {}", name, w_name).green()
+    );
+
+    Ok(())
+}
+
+/// Add the services of a Procfile to workspace `w_name`, running in
+/// directory `dir` or the primary one. The file defaults to the Procfile
+/// of that directory
+pub fn import_procfile(
+    w_name: String,
+    dir: Option<String>,
+    file: Option<PathBuf>,
+    restart: bool,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let dir = match &dir {
+        Some(dir) => find_dir(&ws, dir)?,
+        None => match ws.primary_dir() {
+            Some(dir) => dir,
+            None => {
+                eprintln!(
+                    "{}",
+                    format!("Workspace {} has no directories", w_name).red()
+                );
+                return Err(Error::InvalidPath(String::from("No directory")));
+            }
+        },
+    };
+    let file = file.unwrap_or_else(|| dir.path.join(services::PROCFILE));
+
+    let defined = match services::parse_procfile(&fs::read_to_string(&file)?) {
+        Ok(defined) => defined,
+        Err(err) => {
+            let message = format!("Invalid {}: {}", file.display(), err);
+            eprintln!("{}", message.red());
+            return Err(Error::InvalidFile(message));
+        }
+    };
+
+    for (name, command) in &defined {
+        db::set_service(
+            ws.get_id(),
+            &Service {
+                name: name.clone(),
+                dir_id: Some(dir.id),
+                command: command.clone(),
+                restart,
+                pid: None,
+                started: None,
+            },
+        )?;
+        println!("Service {}: {}", name, command);
+    }
+    println!(
+        "{}",
+        format!(
+            "{} services imported into workspace {}",
+            defined.len(),
+            w_name
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Remove service `name` from workspace `w_name`, refused while it runs
+pub fn remove_service(w_name: String, name: String) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let service = select_services(&ws, std::slice::from_ref(&name))?.remove(0);
+
+    if service.is_running(&services::log_file(&ws, &name)?) {
+        eprintln!(
+            "{}",
+            format!("{} is running, stop it first with `ws down`", name).red()
+        );
+        return Err(Error::CommandFailed(format!("{} is running", name)));
+    }
+
+    db::remove_service(ws.get_id(), &name)?;
+    println!("{}", format!("Service {} removed", name).green());
+
+    Ok(())
+}
+
+/// Summary of [`multi::run_all`] with a row per job
+fn outcome_table(jobs: &[Job], outcomes: &[(Outcome, std::time::Duration)]) -> Table {
+    let mut table = Table::new();
+    table.add_row(row!["Directory", "Result", "Time"]);
+
+    for (job, (outcome, time)) in jobs.iter().zip(outcomes) {
+        let result = match outcome {
+            Outcome::Success => outcome.describe().green(),
+            Outcome::NotRun => outcome.describe().normal(),
+            _ => outcome.describe().red(),
+        };
+        table.add_row(row![
+            job.name,
+            result,
+            format!("{:.1}s", time.as_secs_f64())
+        ]);
+    }
+
+    table
+}
+
+/// Create a worktree with `branch` for every repository of workspace `w_name`
+/// and register them as workspace `w_name@branch`
+pub fn create_branch_workspace(
+    w_name: String,
+    branch: String,
+    location: Option<PathBuf>,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    if !git::is_valid_branch(&branch) {
+        eprintln!("{}", format!("Invalid branch name {}", branch).red());
+        return Err(Error::InvalidPath(format!("Invalid branch {}", branch)));
+    }
+
+    let b_name = format!("{}@{}", w_name, branch);
+    ensure_workspace_is_new(&b_name)?;
+
+    let base = worktree_base(location)?.join(b_name.replace('/', "-"));
+    if base.exists() {
+        eprintln!("{}", format!("{} already exists", base.display()).red());
+        return Err(Error::AlreadyExists(base.display().to_string()));
+    }
+
+    let mut branch_ws = Workspace::new(b_name.clone());
+    branch_ws.editor = ws.editor.clone();
+    branch_ws.env = ws.env.clone();
+
+    let mut created: Vec<PathBuf> = vec![];
+    for dir in ws.dir_iter() {
+        let mut copy = Dir::new(dir.path.clone());
+        copy.label = dir.label.clone();
+        copy.init = dir.init.clone();
+        copy.primary = dir.primary;
+        copy.git = dir.git.clone();
+
+        if git::is_repo(&dir.path) {
+            // directories without a label can share a name
+            let name = dir.short_name();
+            let path = (1..)
+                .map(|i| match i {
+                    1 => base.join(name.as_ref()),
+                    i => base.join(format!("{}-{}", name, i)),
+                })
+                .find(|path| !created.contains(path))
+                .unwrap();
+
+            if let Err(err) = git::add_worktree(&dir.path, &path, &branch) {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Cannot create a worktree for {}: {}",
+                        dir.display_name(),
+                        err
+                    )
+                    .red()
+                );
+                remove_worktrees(&created, &base);
+                return Err(Error::CommandFailed(String::from(
+                    "git worktree add failed",
+                )));
+            }
+
+            println!("{} -> {}", dir.display_name(), path.display());
+            created.push(path.clone());
+            copy.path = path;
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "{} is not a repository, the new workspace uses it as is",
+                    dir.display_name()
+                )
+                .yellow()
+            );
+        }
+
+        branch_ws.add_dir(copy);
+    }
+
+    if let Err(err) = db::create_workspace(&branch_ws) {
+        remove_worktrees(&created, &base);
+        return Err(Error::from(err));
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Workspace {} created, open it with `ws open {}`",
+            b_name, b_name
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Remove the worktrees of workspace `w_name@branch` and the workspace,
+/// refused while a worktree has changes or the branch is not merged unless `force`
+pub fn remove_branch_workspace(w_name: String, branch: String, force: bool) -> Result<(), Error> {
+    let b_name = format!("{}@{}", w_name, branch);
+    let branch_ws = fetch_workspace(&b_name)?;
+
+    // (worktree, repository it belongs to)
+    let worktrees: Vec<(&Path, PathBuf)> = branch_ws
+        .dir_iter()
+        .filter_map(|dir| git::main_worktree(&dir.path).map(|repo| (dir.path.as_path(), repo)))
+        .collect();
+
+    let mut problems = vec![];
+    for (path, repo) in &worktrees {
+        if git::status(path).is_some_and(|status| status.is_dirty()) {
+            problems.push(format!("{} has uncommitted changes", path.display()));
+        }
+        if !git::is_merged(repo, &branch) {
+            problems.push(format!(
+                "{} is not merged into the checked out branch of {}",
+                branch,
+                repo.display()
+            ));
+        }
+    }
+
+    if !problems.is_empty() && !force {
+        for problem in &problems {
+            eprintln!("{}", problem.red());
+        }
+        eprintln!("{}", "Nothing removed, use --force to remove anyway".red());
+        return Err(Error::CommandFailed(String::from("Worktrees not merged")));
+    }
+
+    for (path, repo) in &worktrees {
+        if let Err(err) = git::remove_worktree(path, force) {
+            eprintln!(
+                "{}",
+                format!("Cannot remove {}: {}", path.display(), err).red()
+            );
+            return Err(Error::CommandFailed(String::from(
+                "git worktree remove failed",
+            )));
+        }
+
+        let delete = if force { "-D" } else { "-d" };
+        if let Err(err) = git::git_checked(repo, &["branch", delete, &branch]) {
+            println!(
+                "{}",
+                format!("Branch {} kept in {}: {}", branch, repo.display(), err).yellow()
+            );
+        }
+        println!("{} removed", path.display());
+
+        // the directory the worktrees were created in, once empty
+        if let Some(parent) = path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+
+    db::delete_workspace(b_name.clone())?;
+    println!("{}", format!("Workspace {} removed", b_name).green());
+
+    Ok(())
+}
+
+/// Directory worktrees are created in, `location` or the `worktrees` setting
+fn worktree_base(location: Option<PathBuf>) -> Result<PathBuf, Error> {
+    let configured = match location {
+        Some(location) => Some(location),
+        None => db::get_setting(settings::WORKTREES)?.map(PathBuf::from),
+    };
+
+    match configured {
+        Some(path) => resolve_dir(
+            &path,
+            PathOptions {
+                create: true,
+                ..Default::default()
+            },
+        ),
+        None => dirs::data_local_dir()
+            .map(|data| data.join("ws").join("worktrees"))
+            .ok_or_else(|| Error::InvalidPath(String::from("Cannot find a data directory"))),
+    }
+}
+
+/// Undo the worktrees created so far after a failure
+fn remove_worktrees(created: &[PathBuf], base: &Path) {
+    for path in created {
+        if let Err(err) = git::remove_worktree(path, true) {
+            eprintln!(
+                "{}",
+                format!("Cannot remove {}: {}", path.display(), err).red()
+            );
+        }
+    }
+    let _ = fs::remove_dir(base);
+}
+
+/// Record the checked out state of the repositories of workspace `w_name`
+pub fn save_snapshot(w_name: String, name: Option<String>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let repos = snapshot::capture(&ws);
+
+    if repos.is_empty() {
+        eprintln!(
+            "{}",
+            format!("Workspace {} has no repositories to snapshot", w_name).red()
+        );
+        return Err(Error::InvalidPath(String::from("No repositories")));
+    }
+
+    let name = db::save_snapshot(ws.get_id(), name.as_deref(), &repos)?;
+
+    for repo in &repos {
+        println!("{} {}", repo.path.display(), repo.describe());
+    }
+    if repos.iter().any(|repo| repo.dirty) {
+        println!(
+            "{}",
+            "Uncommitted changes (marked *) are not part of the snapshot".yellow()
+        );
+    }
+    println!(
+        "{}",
+        format!("Snapshot {} of {} saved", name, w_name).green()
+    );
+
+    Ok(())
+}
+
+/// Check out the state recorded in snapshot `name` of workspace `w_name`,
+/// refused while any repository has uncommitted changes
+pub fn restore_snapshot(w_name: String, name: String) -> Result<(), Error> {
+    let snapshot = find_snapshot(&w_name, &name)?;
+
+    let problems: Vec<String> = snapshot
+        .repos
+        .iter()
+        .filter_map(snapshot::check_restore)
+        .collect();
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{}", problem.red());
+        }
+        eprintln!("{}", "Nothing restored".red());
+        return Err(Error::CommandFailed(String::from("Cannot restore")));
+    }
+
+    let mut failed = 0;
+    for repo in &snapshot.repos {
+        match snapshot::restore(repo) {
+            Ok(note) => println!("{} {}", repo.path.display(), note),
+            Err(err) => {
+                failed += 1;
+                eprintln!("{}", format!("{} {}", repo.path.display(), err).red());
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::CommandFailed(format!(
+            "{} repositories not restored",
+            failed
+        )));
+    }
+    println!("{}", format!("Snapshot {} restored", name).green());
+
+    Ok(())
+}
+
+/// List the snapshots of workspace `w_name`, or of all workspaces
+pub fn print_snapshots(w_name: Option<String>) -> Result<(), Error> {
+    if let Some(w_name) = &w_name {
+        ensure_workspace_exists(w_name)?;
+    }
+    let snapshots = db::fetch_snapshots(w_name.as_deref())?;
+
+    let mut table = Table::new();
+    table.add_row(row![
+        "Workspace",
+        "Snapshot",
+        "Created (UTC)",
+        "Repositories"
+    ]);
+
+    for snapshot in snapshots {
+        let repos: Vec<String> = snapshot
+            .repos
+            .iter()
+            .map(|repo| {
+                let name = repo.path.file_name().unwrap_or(repo.path.as_os_str());
+                format!("{} {}", name.to_string_lossy(), repo.describe())
+            })
+            .collect();
+
+        table.add_row(row![
+            snapshot.workspace,
+            snapshot.name,
+            snapshot.created,
+            repos.join("\n")
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Delete snapshot `name` of workspace `w_name`
+pub fn delete_snapshot(w_name: String, name: String) -> Result<(), Error> {
+    if !db::delete_snapshot(&w_name, &name)? {
+        eprintln!(
+            "{}",
+            format!("Workspace {} has no snapshot {}", w_name, name).red()
+        );
+        return Err(Error::NotFound(format!("Snapshot {}", name)));
+    }
+
+    println!("{}", format!("Snapshot {} deleted", name).green());
+
+    Ok(())
+}
+
+fn find_snapshot(w_name: &str, name: &str) -> Result<Snapshot, Error> {
+    ensure_workspace_exists(w_name)?;
+
+    match db::fetch_snapshots(Some(w_name))?
+        .into_iter()
+        .find(|snapshot| snapshot.name == name)
+    {
+        Some(snapshot) => Ok(snapshot),
+        None => {
+            eprintln!(
+                "{}",
+                format!("Workspace {} has no snapshot {}", w_name, name).red()
+            );
+            Err(Error::NotFound(format!("Snapshot {}", name)))
+        }
+    }
+}
+
+/// Recreate workspace `w_name` by cloning its missing repositories from the
+/// recorded remotes, below `root` at their relative locations when given.
+/// With `lazy` they are only cloned when the workspace is opened
+pub fn clone_workspace(
+    w_name: String,
+    root: Option<PathBuf>,
+    lazy: bool,
+    jobs: Option<usize>,
+) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+    let root = match root {
+        Some(root) => Some(resolve_dir(
+            &root,
+            PathOptions {
+                create: true,
+                ..Default::default()
+            },
+        )?),
+        None => None,
+    };
+
+    let mut moves = vec![];
+    let mut missing = vec![];
+    for (dir, target) in clone::targets(&ws, root.as_deref()) {
+        let found = git::identity(&target);
+        let other_repo = match (&found, &dir.git) {
+            (Some(found), Some(recorded)) => !found.matches(recorded),
+            _ => false,
+        };
+
+        if target.exists() && other_repo {
+            println!(
+                "{}",
+                format!("{} is another repository, skipped", target.display()).yellow()
+            );
+            continue;
+        }
+        if !target.exists() {
+            match clone::source(dir) {
+                Some((remote, branch)) => missing.push((
+                    Job {
+                        name: dir.short_name().into_owned(),
+                        dir: target.clone(),
+                    },
+                    remote,
+                    branch,
+                )),
+                None => {
+                    println!(
+                        "{}",
+                        format!("{} has no recorded remote, skipped", dir.display_name()).yellow()
+                    );
+                    continue;
+                }
+            }
+        }
+        if target != dir.path {
+            moves.push((dir.id, target));
+        }
+    }
+
+    db::update_dir_paths(&moves).inspect_err(|err| {
+        eprintln!("{}", format!("Cannot move the directories: {}", err).red());
+    })?;
+
+    if lazy || missing.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "{} directories moved, {} are cloned when the workspace is opened",
+                moves.len(),
+                missing.len()
+            )
+            .green()
+        );
+        return Ok(());
+    }
+
+    let clones: Vec<Job> = missing.iter().map(|(job, _, _)| job.clone()).collect();
+    let threads = jobs.unwrap_or_else(utils::default_threads);
+    let outcomes = multi::run_all(&clones, threads, true, Output::Prefixed, |index, job| {
+        let (_, remote, branch) = &missing[index];
+        if let Some(parent) = job.dir.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        clone::clone_command(remote, *branch, &job.dir)
+    });
+
+    let failed = outcomes.iter().filter(|(o, _)| !o.is_success()).count();
+    if failed > 0 {
+        eprintln!(
+            "{}",
+            format!(
+                "{} of {} repositories not cloned, they are tried again on `ws open`",
+                failed,
+                outcomes.len()
+            )
+            .red()
+        );
+        return Err(Error::CommandFailed(format!("{} clones failed", failed)));
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Workspace {} cloned with {} repositories",
+            w_name,
+            outcomes.len()
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+/// Clone the missing directories of `ws` which have a recorded remote,
+/// failures are reported and the directory stays missing
+fn clone_missing_dirs(ws: &Workspace) {
+    for dir in ws.dir_iter().filter(|dir| !dir.path.exists()) {
+        let Some((remote, branch)) = clone::source(dir) else {
+            continue;
+        };
+
+        println!("Cloning {} into {}", remote, dir.path.display());
+        if let Some(parent) = dir.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        match clone::clone_command(remote, branch, &dir.path).status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!(
+                "{}",
+                format!("Cannot clone {}: git {}", dir.display_name(), status).yellow()
+            ),
+            Err(err) => println!(
+                "{}",
+                format!("Cannot clone {}: {}", dir.display_name(), err).yellow()
+            ),
+        }
+    }
+}
+
+/// Write workspace `w_name` as a VS Code workspace to `output`, or stdout
+pub fn export_vscode(w_name: String, output: Option<PathBuf>) -> Result<(), Error> {
+    let ws = fetch_workspace(&w_name)?;
+
+    match output {
+        Some(output) => {
+            vscode::write(&ws, &output).inspect_err(|err| {
+                eprintln!("{}", err.to_string().red());
+            })?;
+            println!(
+                "{}",
+                format!("Workspace {} exported to {}", w_name, output.display()).green()
+            );
+        }
+        None => {
+            let code_workspace = vscode::from_workspace(&ws, None, Default::default());
+            println!("{}", vscode::to_string(&code_workspace));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the health of all workspaces, with `fix` missing repositories
+/// found again below the roots are updated after asking
+pub fn doctor(fix: bool) -> Result<(), Error> {
+    let spaces = db::fetch_all_workspaces_with_dirs()?;
+
+    // remember the identity of the repositories while they can be seen,
+    // checking alone changes nothing
+    if fix {
+        for dir in spaces.iter().flat_map(|space| space.dir_iter()) {
+            if let Some(identity) = git::identity(&dir.path) {
+                if dir.git.as_ref() != Some(&identity) {
+                    db::update_dir_git_identity(dir.id, &identity)?;
+                }
+            }
+        }
+    }
+
+    let mut commands = vec![(String::from("Default editor"), db::get_editor()?)];
+    for space in &spaces {
+        if let Some(editor) = &space.editor {
+            commands.push((format!("Editor of {}", space.name), editor.clone()));
+        }
+    }
+    commands.push((
+        String::from("Command shell"),
+        String::from(workspace::command_shell()),
+    ));
+    commands.push((String::from("Shell"), workspace::user_shell()));
+
+    let mut findings = doctor::check_binaries(&commands);
+    findings.extend(doctor::check_workspaces(&spaces));
+
+    if findings.is_empty() {
+        println!("{}", "No problems found".green());
+        return Ok(());
+    }
+
+    for finding in &findings {
+        let prefix = match &finding.workspace {
+            Some(name) => format!("[{}] ", name),
+            None => String::new(),
+        };
+        let line = format!("{}{}", prefix, finding.message);
+        match finding.severity {
+            Severity::Error => println!("{} {}", "error:".red().bold(), line),
+            Severity::Warning => println!("{} {}", "warning:".yellow().bold(), line),
+        }
+    }
+
+    println!("{} problems found", findings.len());
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let fixed = fix_moved_repositories(&spaces, &findings, fix)?;
+
+    // lets scripts and CI fail on what is still broken
+    if errors > fixed {
+        return Err(Error::CommandFailed(format!(
+            "{} errors found",
+            errors - fixed
+        )));
+    }
+
+    Ok(())
+}
+
+/// Searches the roots for the repositories of `findings` which are missing
+/// and with `fix` asks whether to update them. Returns how many were updated
+fn fix_moved_repositories(
+    spaces: &[Workspace],
+    findings: &[doctor::Finding],
+    fix: bool,
+) -> Result<usize, Error> {
+    let missing: Vec<(&str, &MissingRepo)> = findings
+        .iter()
+        .filter_map(|f| Some((f.workspace.as_deref()?, f.missing_repo.as_ref()?)))
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    let roots: Vec<PathBuf> = db::fetch_roots()?.into_iter().map(|r| r.path).collect();
+    if roots.is_empty() {
+        println!(
+            "{}",
+            "Add roots with `ws roots add` to search for moved repositories".yellow()
+        );
+        return Ok(0);
+    }
+
+    let candidates = doctor::find_repositories(&roots);
+    let mut fixed = 0;
+
+    for (w_name, repo) in missing {
+        let space = spaces.iter().find(|s| s.name == w_name).unwrap();
+        let found: Vec<&Path> = doctor::find_moved_repository(repo, &candidates)
+            .into_iter()
+            .filter(|path| space.check_dir_already_exists(path).is_none())
+            .collect();
+
+        if found.is_empty() {
+            continue;
+        }
+
+        if !fix {
+            for path in &found {
+                println!(
+                    "{} may have moved to {}, run with --fix to update it",
+                    repo.path.display(),
+                    path.display()
+                );
+            }
+            continue;
+        }
+
+        let question = format!("[{}] {} moved to", w_name, repo.path.display());
+        let options: Vec<String> = found
+            .iter()
+            .map(|p| p.display().to_string())
+            .chain([String::from("(keep as is)")])
+            .collect();
+
+        if let Ok(answer) = Select::new(&question, options).raw_prompt() {
+            if answer.index < found.len() {
+                let path = found[answer.index].to_path_buf();
+                db::update_dir_paths(&[(repo.dir_id, path)])?;
+                println!("{}", "Directory updated".green());
+                fixed += 1;
+            }
+        }
+    }
+
+    Ok(fixed)
+}
+
+/// List all roots and their paths
+pub fn print_roots() -> Result<(), Error> {
+    let roots = db::fetch_roots()?;
+
+    let mut table = Table::new();
+
+    table.add_row(row!["Root", "Path"]);
+
+    for root in roots {
+        table.add_row(row![format!("@{}", root.name), root.path.display()]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Add root `name` or change its path
+pub fn add_root(name: String, path: PathBuf) -> Result<(), Error> {
+    if !roots::is_valid_name(&name) {
+        eprintln!(
+            "{}",
+            format!("Invalid root name {}, use letters, digits, - and _", name).red()
+        );
+        return Err(Error::InvalidPath(format!("Invalid root name {}", name)));
+    }
+
+    let path_options = PathOptions {
+        allow_missing: true,
+        ..Default::default()
+    };
+    let path = resolve_dir(&path, path_options)?;
+
+    db::upsert_root(&name, &path)?;
+
+    println!("{}", format!("@{} = {}", name, path.display()).green());
+
+    Ok(())
+}
+
+/// Remove root `name`
+pub fn remove_root(name: String) -> Result<(), Error> {
+    if !db::remove_root(&name)? {
+        eprintln!("{}", format!("Cannot find root {}", name).red());
+        return Err(Error::NotFound(format!("Root {}", name)));
+    }
+
+    println!("{}", format!("Root @{} removed", name).green());
+
+    Ok(())
+}
+
+/// List the settings with their values
+pub fn print_settings() -> Result<(), Error> {
+    let values = db::fetch_settings()?;
+
+    let mut table = Table::new();
+    table.add_row(row!["Setting", "Value", "Description"]);
+
+    for (name, description) in settings::KNOWN {
+        let value = values.iter().find(|(set, _)| set == name);
+        table.add_row(row![
+            name,
+            value.map_or("", |(_, value)| value),
+            description
+        ]);
+    }
+    for (name, value) in &values {
+        if let Some(host) = settings::host_of(name) {
+            let description = format!("how `ws browse` links to {}", host);
+            table.add_row(row![name, value, description]);
+        }
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+/// Set setting `name` to `value`
+pub fn set_setting(name: String, value: String) -> Result<(), Error> {
+    if !settings::is_known(&name) {
+        eprintln!(
+            "{}",
+            format!("Unknown setting {}, see `ws config`", name).red()
+        );
+        return Err(Error::InvalidFile(format!("Unknown setting {}", name)));
+    }
+    if let Err(err) = settings::check(&name, &value) {
+        eprintln!("{}", err.red());
+        return Err(Error::InvalidFile(err));
+    }
+
+    db::set_setting(&name, &value)?;
+    println!("{}", format!("{} = {}", name, value).green());
+
+    Ok(())
+}
+
+/// Reset setting `name` to its default
+pub fn unset_setting(name: String) -> Result<(), Error> {
+    if !db::unset_setting(&name)? {
+        eprintln!("{}", format!("Setting {} is not set", name).red());
+        return Err(Error::NotFound(format!("Setting {}", name)));
+    }
+
+    println!("{}", format!("{} unset", name).green());
+
+    Ok(())
+}
+
+/// Remove a directory from a workspace :`w_name`
+/// asks which one when `dir` is `None`
+#[allow(clippy::unnecessary_cast)]
+pub fn remove_dir_from_workspace(w_name: String, dir: Option<String>) -> Result<(), Error> {
+    if let Some(dir) = dir {
+        let ws = fetch_workspace(&w_name)?;
+        let dir = find_dir(&ws, &dir)?;
+
+        return match db::remove_dir_from_workspace(dir.id) {
+            Ok(_) => {
+                println!("{}", "Directory deleted".green());
+                Ok(())
+            }
+            Err(er) => {
+                println!("{}", "Error while removing directoy".red());
+                Err(Error::DbError(er.to_string()))
+            }
+        };
+    }
+
+    if let Some(ws) = db::fetch_workspace_with_dirs_by_name(&w_name) {
+        // store a reference of all the directories in the workspace
+        let dirs: Vec<&Dir> = ws.dir_iter().collect();
+
+        // map the directories to `inquire` options
+        let options = ws
+            .dir_iter()
+            .enumerate()
+            .map(|(i, dir)| ListOption::new(i as usize, dir.display_name()))
+            .collect();
+
+        let mut render_config = RenderConfig::default_colored();
+        render_config.option_index_prefix = IndexPrefix::Simple;
+
+        let ans = Select::new("Select the directory to delete", options)
+            .with_render_config(render_config)
+            .prompt();
+
+        if let Ok(ans) = ans {
+            let index = ans.index;
+
+            let dir = dirs[index];
+
+            return match db::remove_dir_from_workspace(dir.id) {
+                Ok(_) => {
+                    println!("{}", "Directory deleted".green());
+                    Ok(())
+                }
+                Err(er) => {
+                    println!("{}", "Error while removing directoy".red());
+                    Err(Error::DbError(er.to_string()))
+                }
+            };
+        } else {
+            println!("{}", "No directory selected".yellow());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db;
+
+    #[test]
+    fn test_list_workspaces() {
+        db::initialize_db().expect("Error Initializing databsae");
+    }
+
+    #[test]
+    fn test_open_workspace() {
+        let _db = db::test_support::use_temp_db();
+        db::test_support::insert_workspace("workspaces", &["."]);
+        db::update_editor(String::from("true")).unwrap();
+
         let name = "workspaces";
 
-        let res = super::open_workspace(String::from(name));
+        let res = super::open_workspace(String::from(name), None);
 
         assert!(res.is_ok());
     }
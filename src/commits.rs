@@ -0,0 +1,305 @@
+use std::cmp::Reverse;
+use std::path::Path;
+use std::str::FromStr;
+
+use colored::Colorize;
+
+use crate::git;
+use crate::utils;
+use crate::workspace::Workspace;
+
+/// Separates the fields of a commit in the output of `git log`
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// How `ws log` prints the commits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(format!("Unknown format {}, use text or markdown", s)),
+        }
+    }
+}
+
+/// Which commits `ws log` shows
+#[derive(Debug, Default)]
+pub struct Filter {
+    /// Passed to `git log --since`, like `yesterday` or `2 days ago`
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Passed to `git log --author`, `me` is the configured `user.email` of
+    /// each repository
+    pub author: Option<String>,
+}
+
+/// One commit of a directory of a workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    /// Short name of the directory
+    pub dir: String,
+    pub hash: String,
+    /// Commit time in seconds since the epoch, used for ordering
+    pub time: i64,
+    /// `YYYY-MM-DD HH:MM` in local time
+    pub date: String,
+    pub author: String,
+    pub subject: String,
+}
+
+impl Commit {
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(7)]
+    }
+
+    /// `YYYY-MM-DD` in local time
+    pub fn day(&self) -> &str {
+        &self.date[..self.date.len().min(10)]
+    }
+}
+
+/// Commits of `dir` on its checked out branch matching `filter`
+pub fn log(name: &str, dir: &Path, filter: &Filter) -> Result<Vec<Commit>, String> {
+    let format = ["%H", "%ct", "%cd", "%an", "%s"].join(&FIELD_SEPARATOR.to_string());
+    let mut args = vec![
+        String::from("log"),
+        String::from("--no-merges"),
+        String::from("--date=format-local:%Y-%m-%d %H:%M"),
+        format!("--format={}", format),
+    ];
+
+    if let Some(since) = &filter.since {
+        args.push(format!("--since={}", since));
+    }
+    if let Some(until) = &filter.until {
+        args.push(format!("--until={}", until));
+    }
+    match filter.author.as_deref() {
+        Some("me") => {
+            let email = git::git(dir, &["config", "user.email"])
+                .filter(|email| !email.is_empty())
+                .ok_or_else(|| String::from("user.email is not configured"))?;
+            args.push(format!("--author=<{}>", email));
+        }
+        Some(author) => args.push(format!("--author={}", author)),
+        None => {}
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = match git::git_checked(dir, &args) {
+        Ok(output) => output,
+        // a repository without any commit
+        Err(err) if err.contains("does not have any commits") => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    Ok(output
+        .lines()
+        .filter_map(|line| parse_line(name, line))
+        .collect())
+}
+
+fn parse_line(dir: &str, line: &str) -> Option<Commit> {
+    let mut fields = line.splitn(5, FIELD_SEPARATOR);
+
+    Some(Commit {
+        dir: String::from(dir),
+        hash: String::from(fields.next()?),
+        time: fields.next()?.parse().ok()?,
+        date: String::from(fields.next()?),
+        author: String::from(fields.next()?),
+        subject: String::from(fields.next()?),
+    })
+}
+
+/// Commits of all repositories of `spaces`, newest first. Repositories which
+/// could not be read are returned with the error
+pub fn collect(spaces: &[Workspace], filter: &Filter) -> (Vec<Commit>, Vec<(String, String)>) {
+    let dirs: Vec<(String, &Path)> = spaces
+        .iter()
+        .flat_map(|ws| ws.dir_iter())
+        .filter(|dir| git::is_repo(&dir.path))
+        .map(|dir| (dir.short_name().into_owned(), dir.path.as_path()))
+        .collect();
+
+    let mut commits = vec![];
+    let mut errors = vec![];
+    let logs = utils::parallel_map(&dirs, utils::default_threads(), |(name, path)| {
+        log(name, path, filter)
+    });
+    for ((name, _), result) in dirs.iter().zip(logs) {
+        match result {
+            Ok(found) => commits.extend(found),
+            Err(err) => errors.push((name.clone(), err)),
+        }
+    }
+
+    // newest first like git log, the sort is stable for commits of the same second
+    commits.sort_by_key(|commit| Reverse(commit.time));
+
+    (commits, errors)
+}
+
+/// One line per commit
+pub fn render(commits: &[Commit], format: Format) -> String {
+    let width = commits.iter().map(|c| c.dir.len()).max().unwrap_or(0);
+
+    commits
+        .iter()
+        .map(|commit| match format {
+            Format::Text => format!(
+                "{} {} {} {} {}\n",
+                commit.date.dimmed(),
+                format!("{:width$}", commit.dir, width = width).cyan(),
+                commit.short_hash().yellow(),
+                commit.subject,
+                format!("({})", commit.author).dimmed()
+            ),
+            Format::Markdown => format!(
+                "- {} **{}** {} (`{}`, {})\n",
+                commit.date,
+                commit.dir,
+                commit.subject,
+                commit.short_hash(),
+                commit.author
+            ),
+        })
+        .collect()
+}
+
+/// The commits grouped by day and directory, with the number of commits
+pub fn render_summary(commits: &[Commit], format: Format) -> String {
+    let mut out = String::new();
+
+    for day in group_by(commits, |commit| commit.day()) {
+        let date = day[0].day();
+        match format {
+            Format::Text => out.push_str(&format!("{}\n", date.bold())),
+            Format::Markdown => out.push_str(&format!("### {}\n\n", date)),
+        }
+
+        let mut dirs: Vec<&str> = day.iter().map(|commit| commit.dir.as_str()).collect();
+        dirs.sort_unstable();
+        dirs.dedup();
+
+        for dir in dirs {
+            let in_dir: Vec<&Commit> = day.iter().filter(|commit| commit.dir == dir).collect();
+            let count = match in_dir.len() {
+                1 => String::from("1 commit"),
+                n => format!("{} commits", n),
+            };
+
+            match format {
+                Format::Text => out.push_str(&format!("  {} {}\n", dir.cyan(), count.dimmed())),
+                Format::Markdown => out.push_str(&format!("- **{}** ({})\n", dir, count)),
+            }
+            for commit in in_dir {
+                match format {
+                    Format::Text => out.push_str(&format!("    {}\n", commit.subject)),
+                    Format::Markdown => out.push_str(&format!("  - {}\n", commit.subject)),
+                }
+            }
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Splits `commits` into runs sharing the same key
+fn group_by<'a, K: PartialEq>(
+    commits: &'a [Commit],
+    key: impl Fn(&'a Commit) -> K,
+) -> Vec<&'a [Commit]> {
+    let mut groups = vec![];
+    let mut start = 0;
+
+    for i in 1..=commits.len() {
+        if i == commits.len() || key(&commits[i]) != key(&commits[start]) {
+            groups.push(&commits[start..i]);
+            start = i;
+        }
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{init_repo, run_git};
+    use crate::workspace::Dir;
+
+    fn commit(dir: &str, time: i64, date: &str, subject: &str) -> Commit {
+        Commit {
+            dir: String::from(dir),
+            hash: format!("{:040}", time),
+            time,
+            date: String::from(date),
+            author: String::from("Alice"),
+            subject: String::from(subject),
+        }
+    }
+
+    #[test]
+    fn merges_the_logs_of_all_repositories() {
+        let root = tempfile::tempdir().unwrap();
+        let (api, web) = (root.path().join("api"), root.path().join("web"));
+        init_repo(&api);
+        init_repo(&web);
+        run_git(&api, &["config", "user.email", "ws@localhost"]);
+        run_git(&web, &["config", "user.email", "bob@localhost"]);
+        run_git(&api, &["commit", "-q", "--allow-empty", "-m", "add login"]);
+
+        let mut ws = Workspace::new(String::from("x"));
+        ws.add_dir(Dir::new(&api));
+        ws.add_dir(Dir::new(&web));
+        ws.add_dir(Dir::new(root.path()));
+
+        let (commits, errors) = collect(std::slice::from_ref(&ws), &Filter::default());
+        assert!(errors.is_empty());
+        assert_eq!(commits.len(), 3);
+        assert_eq!(commits.iter().filter(|c| c.dir == "api").count(), 2);
+        assert!(commits.windows(2).all(|w| w[0].time >= w[1].time));
+
+        let mine = Filter {
+            author: Some(String::from("me")),
+            ..Default::default()
+        };
+        let (commits, errors) = collect(&[ws], &mine);
+        assert!(errors.is_empty());
+        assert_eq!(commits.len(), 2);
+        assert!(commits.iter().all(|c| c.dir == "api" && c.author == "ws"));
+    }
+
+    #[test]
+    fn renders_markdown_and_a_summary_per_day() {
+        let commits = [
+            commit("web", 3, "2026-10-17 16:00", "fix layout"),
+            commit("api", 2, "2026-10-17 11:00", "add login"),
+            commit("web", 1, "2026-10-16 09:30", "start"),
+        ];
+
+        assert_eq!(
+            render(&commits[1..2], Format::Markdown),
+            "- 2026-10-17 11:00 **api** add login (`0000000`, Alice)\n"
+        );
+        assert_eq!(
+            render_summary(&commits, Format::Markdown),
+            "### 2026-10-17\n\n\
+             - **api** (1 commit)\n  - add login\n\
+             - **web** (1 commit)\n  - fix layout\n\n\
+             ### 2026-10-16\n\n\
+             - **web** (1 commit)\n  - start\n\n"
+        );
+    }
+}
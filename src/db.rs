@@ -1,10 +1,144 @@
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 use dirs::home_dir;
-use rusqlite::{params, Connection, Error, Result};
-
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, Error, OptionalExtension, Result, Row};
+
+use crate::git::GitIdentity;
+use crate::project::ProjectFile;
+use crate::roots::{self, Root};
+use crate::services::Service;
+use crate::snapshot::{RepoState, Snapshot};
+use crate::tasks::{self, Origin, Task};
+use crate::utils;
 use crate::workspace::{Dir, Workspace};
 
+/// Schema changes applied on top of the initial tables, oldest first.
+/// `PRAGMA user_version` records how many of them have already run
+const MIGRATIONS: &[&str] = &[
+    // 1: per-workspace editor override
+    "ALTER TABLE workspaces ADD COLUMN editor TEXT;",
+    // 2: dir labels, ordering and primary dir
+    "ALTER TABLE dirs ADD COLUMN label TEXT;
+    ALTER TABLE dirs ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE dirs ADD COLUMN is_primary INTEGER NOT NULL DEFAULT 0;
+    UPDATE dirs SET position = id;",
+    // 3: a directory is part of a workspace only once, the unique index
+    // also serves lookups of the directories of a workspace
+    "DELETE FROM dirs WHERE workspaceId IS NULL
+        OR workspaceId NOT IN (SELECT id FROM workspaces);
+    DELETE FROM dirs WHERE id NOT IN (SELECT MIN(id) FROM dirs GROUP BY workspaceId, path);
+    CREATE UNIQUE INDEX dirs_workspace_path ON dirs(workspaceId, path);",
+    // 4: paths are stored as the bytes of the OS path
+    "UPDATE dirs SET path = CAST(path AS BLOB);",
+    // 5: roots are named directories that stored paths are relative to (`@root/...`)
+    "CREATE TABLE roots (
+        name    TEXT PRIMARY KEY,
+        path    BLOB NOT NULL
+    );",
+    // 6: identity of git repositories to find them again after they moved
+    "ALTER TABLE dirs ADD COLUMN root_commit TEXT;
+    ALTER TABLE dirs ADD COLUMN remote TEXT;",
+    // 7: environment variables of a workspace
+    "CREATE TABLE workspace_env (
+        workspaceId     INTEGER NOT NULL,
+        name            TEXT NOT NULL,
+        value           TEXT NOT NULL,
+        PRIMARY KEY(workspaceId, name),
+        FOREIGN KEY(workspaceId) REFERENCES workspaces(id)
+        ON DELETE CASCADE
+    );",
+    // 8: project files applied to directories and the tasks they define
+    "ALTER TABLE dirs ADD COLUMN project TEXT;
+    ALTER TABLE dirs ADD COLUMN trusted_scripts TEXT;
+    CREATE TABLE dir_tasks (
+        dirId       INTEGER NOT NULL,
+        name        TEXT NOT NULL,
+        command     TEXT NOT NULL,
+        PRIMARY KEY(dirId, name),
+        FOREIGN KEY(dirId) REFERENCES dirs(id)
+        ON DELETE CASCADE
+    );",
+    // 9: settings changed with `ws config`
+    "CREATE TABLE settings (
+        name    TEXT PRIMARY KEY,
+        value   TEXT NOT NULL
+    );",
+    // 10: recorded git state of the repositories of a workspace
+    "CREATE TABLE snapshots (
+        id              INTEGER PRIMARY KEY AUTOINCREMENT,
+        workspaceId     INTEGER NOT NULL,
+        name            TEXT NOT NULL,
+        created         TEXT NOT NULL DEFAULT (datetime('now')),
+        UNIQUE(workspaceId, name),
+        FOREIGN KEY(workspaceId) REFERENCES workspaces(id)
+        ON DELETE CASCADE
+    );
+    CREATE TABLE snapshot_repos (
+        snapshotId      INTEGER NOT NULL,
+        dirId           INTEGER NOT NULL,
+        head            TEXT NOT NULL,
+        branch          TEXT,
+        dirty           INTEGER NOT NULL,
+        PRIMARY KEY(snapshotId, dirId),
+        FOREIGN KEY(snapshotId) REFERENCES snapshots(id)
+        ON DELETE CASCADE,
+        FOREIGN KEY(dirId) REFERENCES dirs(id)
+        ON DELETE CASCADE
+    );",
+    // 11: branch to check out when the repository is cloned again
+    "ALTER TABLE dirs ADD COLUMN default_branch TEXT;",
+    // 12: dependencies between tasks and tasks added to a workspace,
+    // a task without directory runs in every directory
+    "ALTER TABLE dir_tasks ADD COLUMN depends TEXT;
+    CREATE TABLE workspace_tasks (
+        workspaceId     INTEGER NOT NULL,
+        name            TEXT NOT NULL,
+        dirId           INTEGER,
+        command         TEXT NOT NULL,
+        depends         TEXT,
+        PRIMARY KEY(workspaceId, name),
+        FOREIGN KEY(workspaceId) REFERENCES workspaces(id)
+        ON DELETE CASCADE,
+        FOREIGN KEY(dirId) REFERENCES dirs(id)
+        ON DELETE CASCADE
+    );",
+    // 13: background services of a workspace and the process group running them
+    "CREATE TABLE services (
+        workspaceId     INTEGER NOT NULL,
+        name            TEXT NOT NULL,
+        dirId           INTEGER,
+        command         TEXT NOT NULL,
+        restart         INTEGER NOT NULL DEFAULT 0,
+        pid             INTEGER,
+        started         TEXT,
+        PRIMARY KEY(workspaceId, name),
+        FOREIGN KEY(workspaceId) REFERENCES workspaces(id)
+        ON DELETE CASCADE,
+        FOREIGN KEY(dirId) REFERENCES dirs(id)
+        ON DELETE CASCADE
+    );",
+];
+
+/// Columns of `dirs` which describe the directory itself,
+/// copied along when a directory moves to another workspace
+const DIR_DATA_COLUMNS: &str =
+    "path, script, label, is_primary, root_commit, remote, default_branch, project, trusted_scripts";
+
+/// Columns of `dirs d` read by [`dir_from_row`]
+const DIR_SELECT_COLUMNS: &str =
+    "d.id AS did, d.path, d.script, d.label, d.is_primary, d.root_commit, d.remote,
+    d.default_branch, d.project, d.trusted_scripts";
+
+#[cfg(test)]
+thread_local! {
+    /// Database used instead of the one in home directory by tests on this thread
+    static TEST_DB_PATH: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+#[allow(clippy::needless_return)]
 pub fn initialize_db() -> Result<()> {
     let conn = connect_db()?;
     let table_exists;
@@ -27,7 +161,7 @@ pub fn initialize_db() -> Result<()> {
     }
 
     if table_exists {
-        return Ok(());
+        return migrate(&conn);
     }
 
     conn.execute(
@@ -64,18 +198,40 @@ pub fn initialize_db() -> Result<()> {
         params![],
     )?;
 
+    migrate(&conn)
+}
+
+/// Runs the pending [`MIGRATIONS`], each one in its own transaction
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+
     Ok(())
 }
 
+fn db_path() -> PathBuf {
+    #[cfg(test)]
+    if let Some(path) = TEST_DB_PATH.with(|p| p.borrow().clone()) {
+        return path;
+    }
+
+    home_dir().unwrap().join("workspaces.db")
+}
+
 pub fn connect_db() -> Result<Connection> {
-    let path = home_dir().unwrap();
-    let path = path.to_str().unwrap();
-    let path = format!("{}{}", path, "/workspaces.db");
-    let conn = Connection::open(path)?;
+    let conn = Connection::open(db_path())?;
+    conn.pragma_update(None, "foreign_keys", true)?;
 
     Ok(conn)
 }
 
+#[allow(clippy::needless_return)]
 pub fn insert_new_workspace(workspace: Workspace) -> Result<usize> {
     let conn = connect_db()?;
 
@@ -98,6 +254,211 @@ pub fn insert_new_workspace(workspace: Workspace) -> Result<usize> {
     Ok(inserted_id)
 }
 
+/// Inserts `workspace` with its editor and all its directories at once,
+/// duplicate directories are skipped. Returns the id of the new workspace
+pub fn create_workspace(workspace: &Workspace) -> Result<i32> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+    let stored_paths = StoredPaths::load(&tx)?;
+
+    let id = insert_workspace_row(&tx, workspace)?;
+    add_to_workspace(&tx, &stored_paths, id, workspace)?;
+    tx.commit()?;
+
+    Ok(id)
+}
+
+/// Appends the directories and environment variables of `workspace`
+/// to workspace `id`, skipping the ones it already has
+fn add_to_workspace(
+    conn: &Connection,
+    stored_paths: &StoredPaths,
+    id: i32,
+    workspace: &Workspace,
+) -> Result<()> {
+    for dir in workspace.dir_iter() {
+        let git = dir.git.clone().unwrap_or_default();
+        conn.execute(
+            "INSERT OR IGNORE INTO dirs(workspaceId, path, script, label, is_primary,
+                root_commit, remote, default_branch, position)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8,
+                (SELECT COALESCE(MAX(position), 0) + 1 FROM dirs WHERE workspaceId = ?1))",
+            params![
+                id,
+                stored_paths.store(&dir.path),
+                dir.init,
+                dir.label,
+                dir.primary,
+                git.root_commit,
+                git.remote,
+                git.default_branch
+            ],
+        )?;
+    }
+
+    for (name, value) in &workspace.env {
+        conn.execute(
+            "INSERT OR IGNORE INTO workspace_env(workspaceId, name, value) VALUES (?1, ?2, ?3)",
+            params![id, name, value],
+        )?;
+    }
+
+    normalize_dirs(conn, id)
+}
+
+/// How [`write_workspaces`] stores a workspace
+pub enum WorkspaceWrite<'a> {
+    /// Insert a new workspace
+    Create(&'a Workspace),
+    /// Replace the workspace with the same name
+    Replace(&'a Workspace),
+    /// Add new directories and variables to the workspace with the same name,
+    /// the editor is only set when it has none
+    Merge(&'a Workspace),
+    /// Make the workspace with the same name match, directories are matched
+    /// by path and keep their id
+    Update(&'a Workspace),
+    /// Delete the workspace with this name
+    Delete(&'a str),
+}
+
+/// Stores all `writes` at once, nothing is stored when one of them fails
+pub fn write_workspaces(writes: &[WorkspaceWrite]) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+    let stored_paths = StoredPaths::load(&tx)?;
+    let existing_id = |name: &str| find_workspace_id(&tx, name)?.ok_or(Error::QueryReturnedNoRows);
+
+    for write in writes {
+        match write {
+            WorkspaceWrite::Create(workspace) => {
+                let id = insert_workspace_row(&tx, workspace)?;
+                add_to_workspace(&tx, &stored_paths, id, workspace)?;
+            }
+            WorkspaceWrite::Replace(workspace) => {
+                let id = existing_id(&workspace.name)?;
+                tx.execute("DELETE FROM workspaces WHERE id = ?1", params![id])?;
+                let id = insert_workspace_row(&tx, workspace)?;
+                add_to_workspace(&tx, &stored_paths, id, workspace)?;
+            }
+            WorkspaceWrite::Merge(workspace) => {
+                let id = existing_id(&workspace.name)?;
+                tx.execute(
+                    "UPDATE workspaces SET editor = COALESCE(editor, ?2) WHERE id = ?1",
+                    params![id, workspace.editor],
+                )?;
+                add_to_workspace(&tx, &stored_paths, id, workspace)?;
+            }
+            WorkspaceWrite::Update(workspace) => {
+                let id = existing_id(&workspace.name)?;
+                update_workspace(&tx, &stored_paths, id, workspace)?;
+            }
+            WorkspaceWrite::Delete(name) => {
+                let id = existing_id(name)?;
+                tx.execute("DELETE FROM workspaces WHERE id = ?1", params![id])?;
+            }
+        }
+    }
+
+    tx.commit()
+}
+
+fn insert_workspace_row(conn: &Connection, workspace: &Workspace) -> Result<i32> {
+    conn.execute(
+        "INSERT INTO workspaces(name, editor) VALUES (?1, ?2)",
+        params![workspace.name, workspace.editor],
+    )?;
+
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+/// Makes workspace `id` match `workspace`: same editor, variables
+/// and directories in the same order
+fn update_workspace(
+    conn: &Connection,
+    stored_paths: &StoredPaths,
+    id: i32,
+    workspace: &Workspace,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE workspaces SET editor = ?2 WHERE id = ?1",
+        params![id, workspace.editor],
+    )?;
+    conn.execute(
+        "DELETE FROM workspace_env WHERE workspaceId = ?1",
+        params![id],
+    )?;
+
+    let mut stmt = conn.prepare("SELECT id, path FROM dirs WHERE workspaceId = ?1")?;
+    let current = stmt
+        .query_map(params![id], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                stored_paths.resolve(path_from_row(row, 1)?),
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (dir_id, path) in &current {
+        if workspace.check_dir_already_exists(path).is_none() {
+            conn.execute("DELETE FROM dirs WHERE id = ?1", params![dir_id])?;
+        }
+    }
+
+    for (position, dir) in workspace.dir_iter().enumerate() {
+        let position = position as i64 + 1;
+        match current.iter().find(|(_, path)| *path == dir.path) {
+            Some((dir_id, _)) => {
+                // a recorded remote is only filled in, never replaced
+                let git = dir.git.clone().unwrap_or_default();
+                conn.execute(
+                    "UPDATE dirs SET script = ?2, label = ?3, is_primary = ?4, position = ?5,
+                        remote = COALESCE(remote, ?6),
+                        default_branch = COALESCE(default_branch, ?7)
+                    WHERE id = ?1",
+                    params![
+                        dir_id,
+                        dir.init,
+                        dir.label,
+                        dir.primary,
+                        position,
+                        git.remote,
+                        git.default_branch
+                    ],
+                )?;
+            }
+            None => {
+                let git = dir.git.clone().unwrap_or_default();
+                conn.execute(
+                    "INSERT INTO dirs(workspaceId, path, script, label, is_primary, position,
+                        root_commit, remote, default_branch)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        id,
+                        stored_paths.store(&dir.path),
+                        dir.init,
+                        dir.label,
+                        dir.primary,
+                        position,
+                        git.root_commit,
+                        git.remote,
+                        git.default_branch
+                    ],
+                )?;
+            }
+        }
+    }
+
+    for (name, value) in &workspace.env {
+        conn.execute(
+            "INSERT INTO workspace_env(workspaceId, name, value) VALUES (?1, ?2, ?3)",
+            params![id, name, value],
+        )?;
+    }
+
+    normalize_dirs(conn, id)
+}
+
 /// Delete a workspace from database
 pub fn delete_workspace(name: String) -> Result<()> {
     let conn = connect_db()?;
@@ -119,11 +480,11 @@ pub fn update_editor(editor: String) -> Result<()> {
     if count == 0 {
         let mut create_stmt = conn.prepare(
             "
-    INSERT INTO editor(editor) VALUES('code');
+    INSERT INTO editor(editor) VALUES(?1);
 ",
         )?;
 
-        create_stmt.execute(params![])?;
+        create_stmt.execute(params![editor])?;
     } else {
         let mut stmt = conn.prepare("UPDATE editor SET editor = ?1")?;
 
@@ -133,7 +494,25 @@ pub fn update_editor(editor: String) -> Result<()> {
     Ok(())
 }
 
+/// Override the editor for a single workspace,
+/// `None` falls back to the default editor again
+pub fn update_workspace_editor(name: &str, editor: Option<String>) -> Result<()> {
+    let conn = connect_db()?;
+
+    let rows = conn.execute(
+        "UPDATE workspaces SET editor = ?2 WHERE name = ?1",
+        params![name, editor],
+    )?;
+
+    if rows == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+
+    Ok(())
+}
+
 /// Get default workspace open editor
+#[allow(clippy::needless_return)]
 pub fn get_editor() -> Result<String> {
     let conn = connect_db()?;
 
@@ -145,8 +524,6 @@ pub fn get_editor() -> Result<String> {
         Ok(editor)
     });
 
-    println!("RESULT {:?}", result);
-
     if let Ok(editor) = result {
         return Ok(editor);
     } else {
@@ -156,18 +533,20 @@ pub fn get_editor() -> Result<String> {
 
 /// Fetches `Dir` for `Workspace` with Id
 #[allow(dead_code)]
-pub fn get_dirs_for_workspace(workspace_id: i32) -> Result<Vec<(i32, String)>> {
+#[allow(clippy::needless_return)]
+pub fn get_dirs_for_workspace(workspace_id: i32) -> Result<Vec<(i32, PathBuf)>> {
     let conn = connect_db()?;
 
     let mut stmt = conn.prepare("SELECT d.id, d.path from dirs d where workspaceId = ?1")?;
 
+    let stored_paths = StoredPaths::load(&conn)?;
     let paths = stmt.query_map([workspace_id], |row| {
-        let path: String = row.get(1).unwrap();
+        let path = stored_paths.resolve(path_from_row(row, 1)?);
         let id: i32 = row.get(0).unwrap();
         return Ok((id, path));
     })?;
 
-    let paths: Vec<(i32, String)> = paths.map(|x| x.unwrap()).collect();
+    let paths: Vec<(i32, PathBuf)> = paths.map(|x| x.unwrap()).collect();
 
     Ok(paths)
 }
@@ -185,21 +564,24 @@ pub fn remove_dir_from_workspace(dir_id: i32) -> Result<()> {
     Ok(())
 }
 
-pub fn insert_new_dir_for_workspace(workspace_id: i32, path: String) -> Result<usize, Error> {
+#[allow(clippy::needless_question_mark)]
+pub fn insert_new_dir_for_workspace(workspace_id: i32, path: &Path) -> Result<usize, Error> {
     let conn = connect_db()?;
+    let stored_paths = StoredPaths::load(&conn)?;
 
     conn.execute(
         "
-        INSERT INTO dirs(workspaceId, path) VALUES(
-            ?1, ?2
+        INSERT INTO dirs(workspaceId, path, position) VALUES(
+            ?1, ?2, (SELECT COALESCE(MAX(position), 0) + 1 FROM dirs WHERE workspaceId = ?1)
         );
     ",
-        params![workspace_id, path],
+        params![workspace_id, stored_paths.store(path)],
     )?;
 
     Ok(get_last_insert_id(conn)?)
 }
 
+#[allow(clippy::needless_return)]
 fn get_last_insert_id(conn: Connection) -> Result<usize> {
     let mut inserted_id = 0;
     conn.query_row("SELECT last_insert_rowid()", params![], |row| {
@@ -212,6 +594,7 @@ fn get_last_insert_id(conn: Connection) -> Result<usize> {
 }
 
 #[allow(dead_code)]
+#[allow(clippy::needless_return)]
 pub fn fetch_all_workspaces() -> Result<Vec<(i32, String)>, Error> {
     let conn = connect_db().unwrap();
 
@@ -229,30 +612,96 @@ pub fn fetch_all_workspaces() -> Result<Vec<(i32, String)>, Error> {
     return Ok(values);
 }
 
+/// Reads a path stored by [`utils::path_to_bytes`],
+/// rows written as text before are accepted as well
+fn path_from_row<I: rusqlite::RowIndex>(row: &Row, index: I) -> Result<PathBuf> {
+    match row.get_ref(index)? {
+        ValueRef::Blob(bytes) | ValueRef::Text(bytes) => Ok(utils::bytes_to_path(bytes.to_vec())),
+        other => Err(Error::InvalidColumnType(
+            0,
+            String::from("path"),
+            other.data_type(),
+        )),
+    }
+}
+
+/// Converts between absolute directory paths and the portable form
+/// they are stored in, relative to a root or the home directory
+struct StoredPaths {
+    roots: Vec<Root>,
+    home: Option<PathBuf>,
+}
+
+impl StoredPaths {
+    fn load(conn: &Connection) -> Result<Self> {
+        Ok(StoredPaths {
+            roots: load_roots(conn)?,
+            home: home_dir(),
+        })
+    }
+
+    fn store(&self, path: &Path) -> Vec<u8> {
+        utils::path_to_bytes(&roots::to_portable(path, &self.roots, self.home.as_deref()))
+    }
+
+    fn resolve(&self, stored: PathBuf) -> PathBuf {
+        roots::from_portable(&stored, &self.roots, self.home.as_deref())
+    }
+
+    /// Checks if `stored` is relative to one of the roots
+    fn is_under_root(&self, stored: &Path) -> bool {
+        roots::from_portable(stored, &self.roots, None) != stored
+    }
+}
+
+/// Builds a [`Dir`] from a row selecting [`DIR_SELECT_COLUMNS`]
+fn dir_from_row(row: &Row, stored_paths: &StoredPaths) -> Result<Dir> {
+    let git = GitIdentity {
+        root_commit: row.get("root_commit")?,
+        remote: row.get("remote")?,
+        default_branch: row.get("default_branch")?,
+    };
+
+    Ok(Dir {
+        git: (git != GitIdentity::default()).then_some(git),
+        id: row.get("did")?,
+        path: stored_paths.resolve(path_from_row(row, "path")?),
+        init: row.get("script")?,
+        label: row.get("label")?,
+        primary: row.get("is_primary")?,
+        project: row.get("project")?,
+        trusted_scripts: row.get("trusted_scripts")?,
+    })
+}
+
+#[allow(clippy::needless_return)]
 pub fn fetch_workspace_with_dirs_by_name(name: &str) -> Option<Workspace> {
     let conn = connect_db().unwrap();
 
     let workspace_name = name;
 
     let mut stmt = conn
-        .prepare(
-            "SELECT w.name, w.id, d.path, d.id as did FROM workspaces w
+        .prepare(&format!(
+            "SELECT w.name, w.id, w.editor, {} FROM workspaces w
         INNER JOIN dirs d on w.id = d.workspaceId
         WHERE w.name == ?1
+        ORDER BY d.position, d.id
         ",
-        )
+            DIR_SELECT_COLUMNS
+        ))
         .expect("Statement Failed");
 
+    let stored_paths = StoredPaths::load(&conn).expect("Can't load roots");
     let id: RefCell<i32> = RefCell::new(0);
+    let editor: RefCell<Option<String>> = RefCell::new(None);
     let res = stmt.query_map([workspace_name], |row| {
         let w_name: String = row.get("name").expect("Can't get name");
         let _id: i32 = row.get("id").expect("Can't get id");
-        let path: String = row.get("path").expect("Can't get path");
-        let did: i32 = row.get("did").expect("did error");
-        let script: String = row.get("script").unwrap_or(String::from("code ."));
+        let dir = dir_from_row(row, &stored_paths)?;
 
         *id.borrow_mut() = _id;
-        return Ok((w_name, _id, path.clone(), did, script));
+        *editor.borrow_mut() = row.get("editor")?;
+        return Ok((w_name, _id, dir));
     });
 
     match res {
@@ -261,12 +710,7 @@ pub fn fetch_workspace_with_dirs_by_name(name: &str) -> Option<Workspace> {
             let mut count = 0;
             for p in res {
                 match p {
-                    Ok(value) => {
-                        let dir = Dir {
-                            path: value.2,
-                            id: value.3,
-                            init: Some(String::from("asd")),
-                        };
+                    Ok((_, _, dir)) => {
                         ws.add_dir(dir);
                         count += 1;
                     }
@@ -280,6 +724,11 @@ pub fn fetch_workspace_with_dirs_by_name(name: &str) -> Option<Workspace> {
             }
 
             ws.set_id(*id.borrow());
+            ws.editor = editor.take();
+            ws.env = load_env(&conn)
+                .expect("Can't load env")
+                .remove(&ws.get_id())
+                .unwrap_or_default();
 
             Some(ws)
         }
@@ -317,46 +766,894 @@ pub fn _fetch_all_workspaces_with_dirs() -> Result<Vec<Workspace>> {
 
 /// Queries the database and returns a Vec of Workspace
 /// with the related directories built in
+#[allow(
+    clippy::let_unit_value,
+    clippy::needless_late_init,
+    clippy::needless_return
+)]
 pub fn fetch_all_workspaces_with_dirs() -> Result<Vec<Workspace>> {
     let conn = connect_db()?;
 
     let mut stmt = conn
-        .prepare(
-            "SELECT w.name, w.id, d.path from workspaces w
+        .prepare(&format!(
+            "SELECT w.name, w.id, w.editor, {} from workspaces w
             LEFT JOIN dirs d
-            ON d.workspaceId == w.id",
-        )
+            ON d.workspaceId == w.id
+            ORDER BY w.id, d.position, d.id",
+            DIR_SELECT_COLUMNS
+        ))
         .unwrap();
 
+    let stored_paths = StoredPaths::load(&conn)?;
+
     let mut workspaces: Vec<Workspace> = vec![];
 
     let _items = stmt
         .query_map(params![], |x| {
             let val: String = x.get(0).unwrap();
             let id: i32 = x.get(1).unwrap();
-            let path: String = x.get(2).unwrap_or(String::from("None"));
-
-            Ok((val, id, path))
+            let editor: Option<String> = x.get(2)?;
+            let did: Option<i32> = x.get("did")?;
+            let dir = match did {
+                Some(_) => Some(dir_from_row(x, &stored_paths)?),
+                None => None,
+            };
+
+            Ok((val, id, editor, dir))
         })?
         .for_each(|value_res| {
-            if let Ok((val, id, path)) = value_res {
+            if let Ok((val, id, editor, dir)) = value_res {
                 if let Some(position) = workspaces.iter().position(|w| w.get_id() == id) {
                     let ws = workspaces.get_mut(position).unwrap();
-                    ws.add_dir(Dir::new(path));
+                    ws.add_dir(dir.unwrap());
                 } else {
                     let space: Workspace;
                     let mut ws = Workspace::new(val.clone());
                     ws.set_id(id);
-                    ws.add_dir(Dir::new(path));
+                    ws.editor = editor;
+                    if let Some(dir) = dir {
+                        ws.add_dir(dir);
+                    }
                     space = ws;
                     workspaces.push(space);
                 }
             }
         });
 
+    let mut env = load_env(&conn)?;
+    for ws in workspaces.iter_mut() {
+        ws.env = env.remove(&ws.get_id()).unwrap_or_default();
+    }
+
     return Ok(workspaces);
 }
 
+/// Checks if a workspace named `name` exists, with or without directories
+pub fn workspace_exists(name: &str) -> Result<bool> {
+    let conn = connect_db()?;
+
+    Ok(find_workspace_id(&conn, name)?.is_some())
+}
+
+fn find_workspace_id(conn: &Connection, name: &str) -> Result<Option<i32>> {
+    conn.query_row(
+        "SELECT id FROM workspaces WHERE name = ?1",
+        params![name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+fn create_workspace_like(conn: &Connection, name: &str, template_id: i32) -> Result<i32> {
+    conn.execute(
+        "INSERT INTO workspaces(name, editor) SELECT ?1, editor FROM workspaces WHERE id = ?2",
+        params![name, template_id],
+    )?;
+    let id = conn.last_insert_rowid() as i32;
+
+    copy_env(conn, template_id, id)?;
+
+    Ok(id)
+}
+
+/// Copies the environment variables of workspace `from`
+/// which workspace `to` does not set yet
+fn copy_env(conn: &Connection, from: i32, to: i32) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO workspace_env(workspaceId, name, value)
+        SELECT ?2, name, value FROM workspace_env WHERE workspaceId = ?1",
+        params![from, to],
+    )?;
+
+    Ok(())
+}
+
+/// Copies the tasks of the directories of workspace `from`
+/// to the directories with the same path in workspace `to`
+fn copy_dir_tasks(conn: &Connection, from: i32, to: i32) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO dir_tasks(dirId, name, command, depends)
+        SELECT new.id, t.name, t.command, t.depends FROM dir_tasks t
+        INNER JOIN dirs old ON old.id = t.dirId AND old.workspaceId = ?1
+        INNER JOIN dirs new ON new.path = old.path AND new.workspaceId = ?2",
+        params![from, to],
+    )?;
+
+    Ok(())
+}
+
+/// Copies the tasks of workspace `from` which workspace `to` does not have
+/// yet, tasks of a directory follow it to the directory with the same path
+fn copy_workspace_tasks(conn: &Connection, from: i32, to: i32) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO workspace_tasks(workspaceId, name, dirId, command, depends)
+        SELECT ?2, t.name, new.id, t.command, t.depends FROM workspace_tasks t
+        LEFT JOIN dirs old ON old.id = t.dirId
+        LEFT JOIN dirs new ON new.path = old.path AND new.workspaceId = ?2
+        WHERE t.workspaceId = ?1 AND (t.dirId IS NULL OR new.id IS NOT NULL)",
+        params![from, to],
+    )?;
+
+    Ok(())
+}
+
+/// Environment variables of all workspaces by workspace id
+fn load_env(conn: &Connection) -> Result<HashMap<i32, BTreeMap<String, String>>> {
+    let mut stmt = conn.prepare("SELECT workspaceId, name, value FROM workspace_env")?;
+    let mut env: HashMap<i32, BTreeMap<String, String>> = HashMap::new();
+
+    for row in stmt.query_map(params![], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))? {
+        let (id, name, value) = row?;
+        env.entry(id).or_default().insert(name, value);
+    }
+
+    Ok(env)
+}
+
+/// Renames workspace `old` to `new`, directories stay attached to it
+pub fn rename_workspace(old: &str, new: &str) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    let rows = tx.execute(
+        "UPDATE workspaces SET name = ?2 WHERE name = ?1",
+        params![old, new],
+    )?;
+
+    if rows == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+
+    tx.commit()
+}
+
+/// Creates workspace `dst` with a copy of every directory of `src`
+/// Returns the id of the new workspace
+pub fn copy_workspace(src: &str, dst: &str) -> Result<i32> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    let src_id = find_workspace_id(&tx, src)?.ok_or(Error::QueryReturnedNoRows)?;
+    let dst_id = create_workspace_like(&tx, dst, src_id)?;
+
+    tx.execute(
+        &format!(
+            "INSERT INTO dirs(workspaceId, position, {cols})
+            SELECT ?2, position, {cols} FROM dirs WHERE workspaceId = ?1 ORDER BY id",
+            cols = DIR_DATA_COLUMNS
+        ),
+        params![src_id, dst_id],
+    )?;
+    copy_dir_tasks(&tx, src_id, dst_id)?;
+    copy_workspace_tasks(&tx, src_id, dst_id)?;
+
+    tx.commit()?;
+
+    Ok(dst_id)
+}
+
+/// Merges all `sources` into workspace `into`, creating it when missing
+/// Directories already present in `into` are skipped, merged sources are deleted
+/// Returns the number of directories in the merged workspace
+pub fn merge_workspaces(sources: &[String], into: &str) -> Result<usize> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    let mut source_ids = vec![];
+    for source in sources {
+        source_ids.push(find_workspace_id(&tx, source)?.ok_or(Error::QueryReturnedNoRows)?);
+    }
+
+    let into_id = match find_workspace_id(&tx, into)? {
+        Some(id) => id,
+        None => create_workspace_like(&tx, into, source_ids[0])?,
+    };
+
+    for source_id in source_ids.into_iter().filter(|id| *id != into_id) {
+        tx.execute(
+            &format!(
+                "INSERT INTO dirs(workspaceId, position, {cols})
+                SELECT ?2, position + (SELECT COALESCE(MAX(position), 0) FROM dirs WHERE workspaceId = ?2),
+                {cols} FROM dirs WHERE workspaceId = ?1
+                AND path NOT IN (SELECT path FROM dirs WHERE workspaceId = ?2)
+                ORDER BY position, id",
+                cols = DIR_DATA_COLUMNS
+            ),
+            params![source_id, into_id],
+        )?;
+
+        tx.execute(
+            "UPDATE workspaces SET editor = (SELECT editor FROM workspaces WHERE id = ?1)
+            WHERE id = ?2 AND editor IS NULL",
+            params![source_id, into_id],
+        )?;
+        copy_env(&tx, source_id, into_id)?;
+        copy_dir_tasks(&tx, source_id, into_id)?;
+        copy_workspace_tasks(&tx, source_id, into_id)?;
+
+        tx.execute(
+            "DELETE FROM dirs WHERE workspaceId = ?1",
+            params![source_id],
+        )?;
+        tx.execute("DELETE FROM workspaces WHERE id = ?1", params![source_id])?;
+    }
+
+    normalize_dirs(&tx, into_id)?;
+
+    let count: usize = tx.query_row(
+        "SELECT COUNT(*) FROM dirs WHERE workspaceId = ?1",
+        params![into_id],
+        |row| row.get(0),
+    )?;
+
+    tx.commit()?;
+
+    Ok(count)
+}
+
+/// Moves the directories with `dir_ids` out of workspace `from` into `into`,
+/// creating `into` when missing. Directories already present in `into` are
+/// only removed from `from`
+pub fn split_workspace(from: &str, dir_ids: &[i32], into: &str) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    let from_id = find_workspace_id(&tx, from)?.ok_or(Error::QueryReturnedNoRows)?;
+    let into_id = match find_workspace_id(&tx, into)? {
+        Some(id) => id,
+        None => create_workspace_like(&tx, into, from_id)?,
+    };
+
+    for dir_id in dir_ids {
+        let duplicate: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM dirs WHERE workspaceId = ?2
+            AND path = (SELECT path FROM dirs WHERE id = ?1))",
+            params![dir_id, into_id],
+            |row| row.get(0),
+        )?;
+
+        let rows = if duplicate {
+            tx.execute(
+                "DELETE FROM dirs WHERE id = ?1 AND workspaceId = ?2",
+                params![dir_id, from_id],
+            )?
+        } else {
+            tx.execute(
+                "UPDATE dirs SET workspaceId = ?3,
+                position = (SELECT COALESCE(MAX(position), 0) + 1 FROM dirs WHERE workspaceId = ?3)
+                WHERE id = ?1 AND workspaceId = ?2",
+                params![dir_id, from_id, into_id],
+            )?
+        };
+
+        if rows == 0 {
+            return Err(Error::QueryReturnedNoRows);
+        }
+    }
+
+    normalize_dirs(&tx, into_id)?;
+
+    tx.commit()
+}
+
+/// Keeps labels unique and at most one primary directory in a workspace
+/// after directories from elsewhere were added to it, the first directory wins
+fn normalize_dirs(conn: &Connection, workspace_id: i32) -> Result<()> {
+    conn.execute(
+        "UPDATE dirs SET label = NULL WHERE workspaceId = ?1 AND label IS NOT NULL
+        AND id != (SELECT o.id FROM dirs o WHERE o.workspaceId = ?1 AND o.label = dirs.label
+            ORDER BY o.position, o.id LIMIT 1)",
+        params![workspace_id],
+    )?;
+
+    conn.execute(
+        "UPDATE dirs SET is_primary = 0 WHERE workspaceId = ?1 AND is_primary
+        AND id != (SELECT o.id FROM dirs o WHERE o.workspaceId = ?1 AND o.is_primary
+            ORDER BY o.position, o.id LIMIT 1)",
+        params![workspace_id],
+    )?;
+
+    Ok(())
+}
+
+/// Sets the label of directory `dir_id`, `None` removes it
+pub fn update_dir_label(dir_id: i32, label: Option<String>) -> Result<()> {
+    let conn = connect_db()?;
+
+    let rows = conn.execute(
+        "UPDATE dirs SET label = ?2 WHERE id = ?1",
+        params![dir_id, label],
+    )?;
+
+    if rows == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+
+    Ok(())
+}
+
+/// Makes `dir_id` the only primary directory of workspace `workspace_id`
+pub fn set_primary_dir(workspace_id: i32, dir_id: i32) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "UPDATE dirs SET is_primary = (id = ?2) WHERE workspaceId = ?1",
+        params![workspace_id, dir_id],
+    )?;
+
+    tx.commit()
+}
+
+/// Stores the order of the directories of a workspace,
+/// `dir_ids` lists all of them in the new order
+pub fn reorder_dirs(workspace_id: i32, dir_ids: &[i32]) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    for (position, dir_id) in dir_ids.iter().enumerate() {
+        let rows = tx.execute(
+            "UPDATE dirs SET position = ?3 WHERE id = ?2 AND workspaceId = ?1",
+            params![workspace_id, dir_id, position + 1],
+        )?;
+
+        if rows == 0 {
+            return Err(Error::QueryReturnedNoRows);
+        }
+    }
+
+    tx.commit()
+}
+
+/// Applies project file `new`, given with its text, to directory `dir_id`
+/// and removes what the previously applied file `old` set
+pub fn apply_project(
+    dir_id: i32,
+    old: Option<&ProjectFile>,
+    new: Option<(&str, &ProjectFile)>,
+) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+    let empty = ProjectFile::default();
+    let old = old.unwrap_or(&empty);
+    let (text, new) = match new {
+        Some((text, new)) => (Some(text), new),
+        None => (None, &empty),
+    };
+
+    let workspace_id: i32 = tx.query_row(
+        "SELECT workspaceId FROM dirs WHERE id = ?1",
+        params![dir_id],
+        |row| row.get(0),
+    )?;
+
+    for (column, old_value, new_value) in [
+        ("script", &old.script, &new.script),
+        ("label", &old.label, &new.label),
+    ] {
+        match (old_value, new_value) {
+            (_, Some(value)) => tx.execute(
+                &format!("UPDATE dirs SET {} = ?2 WHERE id = ?1", column),
+                params![dir_id, value],
+            )?,
+            // only remove values the user did not change since
+            (Some(value), None) => tx.execute(
+                &format!(
+                    "UPDATE dirs SET {0} = NULL WHERE id = ?1 AND {0} = ?2",
+                    column
+                ),
+                params![dir_id, value],
+            )?,
+            (None, None) => 0,
+        };
+    }
+
+    for (name, value) in old
+        .env
+        .iter()
+        .filter(|(name, _)| !new.env.contains_key(*name))
+    {
+        tx.execute(
+            "DELETE FROM workspace_env WHERE workspaceId = ?1 AND name = ?2 AND value = ?3",
+            params![workspace_id, name, value],
+        )?;
+    }
+    for (name, value) in &new.env {
+        tx.execute(
+            "INSERT OR REPLACE INTO workspace_env(workspaceId, name, value) VALUES (?1, ?2, ?3)",
+            params![workspace_id, name, value],
+        )?;
+    }
+
+    tx.execute("DELETE FROM dir_tasks WHERE dirId = ?1", params![dir_id])?;
+    for (name, task) in &new.tasks {
+        tx.execute(
+            "INSERT INTO dir_tasks(dirId, name, command, depends) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                dir_id,
+                name,
+                task.command(),
+                tasks::join_depends(task.depends())
+            ],
+        )?;
+    }
+
+    tx.execute(
+        "UPDATE dirs SET project = ?2 WHERE id = ?1",
+        params![dir_id, text],
+    )?;
+
+    normalize_dirs(&tx, workspace_id)?;
+
+    tx.commit()
+}
+
+/// Records that the user trusts `scripts` of the project file of `dir_id`
+pub fn trust_scripts(dir_id: i32, scripts: &str) -> Result<()> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "UPDATE dirs SET trusted_scripts = ?2 WHERE id = ?1",
+        params![dir_id, scripts],
+    )?;
+
+    Ok(())
+}
+
+/// Records the identity of the git repository in directory `dir_id`
+pub fn update_dir_git_identity(dir_id: i32, identity: &GitIdentity) -> Result<()> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "UPDATE dirs SET root_commit = ?2, remote = ?3, default_branch = ?4 WHERE id = ?1",
+        params![
+            dir_id,
+            identity.root_commit,
+            identity.remote,
+            identity.default_branch
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Changes the paths of directories, given as `(dir id, new path)`, all or nothing
+pub fn update_dir_paths(changes: &[(i32, PathBuf)]) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+    let stored_paths = StoredPaths::load(&tx)?;
+
+    for (id, path) in changes {
+        let rows = tx.execute(
+            "UPDATE dirs SET path = ?2 WHERE id = ?1",
+            params![id, stored_paths.store(path)],
+        )?;
+
+        if rows == 0 {
+            return Err(Error::QueryReturnedNoRows);
+        }
+    }
+
+    tx.commit()
+}
+
+fn load_roots(conn: &Connection) -> Result<Vec<Root>> {
+    let mut stmt = conn.prepare("SELECT name, path FROM roots ORDER BY name")?;
+
+    let roots = stmt.query_map(params![], |row| {
+        Ok(Root {
+            name: row.get(0)?,
+            path: path_from_row(row, 1)?,
+        })
+    })?;
+
+    roots.collect()
+}
+
+/// Fetches all roots directory paths are stored relative to
+pub fn fetch_roots() -> Result<Vec<Root>> {
+    let conn = connect_db()?;
+
+    load_roots(&conn)
+}
+
+/// Adds root `name` or moves it to `path`, directories stored relative
+/// to it move along, other directories below `path` are rewritten to use it
+pub fn upsert_root(name: &str, path: &Path) -> Result<()> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    let before = StoredPaths::load(&tx)?;
+    tx.execute(
+        "INSERT INTO roots(name, path) VALUES (?1, ?2)
+        ON CONFLICT(name) DO UPDATE SET path = excluded.path",
+        params![name, utils::path_to_bytes(path)],
+    )?;
+    restore_dir_paths(&tx, &before)?;
+
+    tx.commit()
+}
+
+/// Removes root `name`, directories stored relative to it
+/// are rewritten to absolute (or home relative) paths
+/// Returns false if there is no such root
+pub fn remove_root(name: &str) -> Result<bool> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    let before = StoredPaths::load(&tx)?;
+    let rows = tx.execute("DELETE FROM roots WHERE name = ?1", params![name])?;
+    restore_dir_paths(&tx, &before)?;
+
+    tx.commit()?;
+
+    Ok(rows > 0)
+}
+
+/// Re-encodes every stored path which was written with `before` and
+/// is not relative to one of the current roots according to these roots
+fn restore_dir_paths(conn: &Connection, before: &StoredPaths) -> Result<()> {
+    let after = StoredPaths::load(conn)?;
+
+    let mut stmt = conn.prepare("SELECT id, path FROM dirs")?;
+    let rows = stmt
+        .query_map(params![], |row| {
+            Ok((row.get::<_, i32>(0)?, path_from_row(row, 1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (id, stored) in rows {
+        if after.is_under_root(&stored) {
+            continue;
+        }
+
+        let path = before.resolve(stored.clone());
+        let restored = after.store(&path);
+        if restored != utils::path_to_bytes(&stored) {
+            conn.execute(
+                "UPDATE dirs SET path = ?2 WHERE id = ?1",
+                params![id, restored],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Saves the state of the repositories of workspace `workspace_id` as snapshot
+/// `name`, named after the current time when `None`. Returns the name
+pub fn save_snapshot(workspace_id: i32, name: Option<&str>, repos: &[RepoState]) -> Result<String> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO snapshots(workspaceId, name)
+        VALUES (?1, COALESCE(?2, strftime('%Y-%m-%d-%H%M%S', 'now')))",
+        params![workspace_id, name],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    for repo in repos {
+        tx.execute(
+            "INSERT INTO snapshot_repos(snapshotId, dirId, head, branch, dirty)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, repo.dir_id, repo.head, repo.branch, repo.dirty],
+        )?;
+    }
+
+    let name = tx.query_row(
+        "SELECT name FROM snapshots WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )?;
+    tx.commit()?;
+
+    Ok(name)
+}
+
+/// Snapshots of workspace `workspace`, or of all workspaces, oldest first
+pub fn fetch_snapshots(workspace: Option<&str>) -> Result<Vec<Snapshot>> {
+    let conn = connect_db()?;
+    let stored_paths = StoredPaths::load(&conn)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT s.id, w.name, s.name, s.created FROM snapshots s
+        JOIN workspaces w ON w.id = s.workspaceId
+        WHERE ?1 IS NULL OR w.name = ?1
+        ORDER BY w.name, s.created, s.id",
+    )?;
+    let snapshots = stmt
+        .query_map(params![workspace], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                Snapshot {
+                    workspace: row.get(1)?,
+                    name: row.get(2)?,
+                    created: row.get(3)?,
+                    repos: vec![],
+                },
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut repo_stmt = conn.prepare(
+        "SELECT r.dirId, d.path, r.head, r.branch, r.dirty FROM snapshot_repos r
+        JOIN dirs d ON d.id = r.dirId
+        WHERE r.snapshotId = ?1
+        ORDER BY d.position, d.id",
+    )?;
+
+    snapshots
+        .into_iter()
+        .map(|(id, mut snapshot)| {
+            snapshot.repos = repo_stmt
+                .query_map(params![id], |row| {
+                    Ok(RepoState {
+                        dir_id: row.get(0)?,
+                        path: stored_paths.resolve(path_from_row(row, 1)?),
+                        head: row.get(2)?,
+                        branch: row.get(3)?,
+                        dirty: row.get(4)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>>>()?;
+            Ok(snapshot)
+        })
+        .collect()
+}
+
+/// Deletes snapshot `name` of workspace `workspace`, returns false if there is none
+pub fn delete_snapshot(workspace: &str, name: &str) -> Result<bool> {
+    let conn = connect_db()?;
+
+    let rows = conn.execute(
+        "DELETE FROM snapshots WHERE name = ?2
+        AND workspaceId = (SELECT id FROM workspaces WHERE name = ?1)",
+        params![workspace, name],
+    )?;
+
+    Ok(rows > 0)
+}
+
+/// Value of setting `name`, `None` when it is not set
+pub fn get_setting(name: &str) -> Result<Option<String>> {
+    let conn = connect_db()?;
+
+    conn.query_row(
+        "SELECT value FROM settings WHERE name = ?1",
+        params![name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// All settings as `(name, value)`, ordered by name
+pub fn fetch_settings() -> Result<Vec<(String, String)>> {
+    let conn = connect_db()?;
+    let mut stmt = conn.prepare("SELECT name, value FROM settings ORDER BY name")?;
+
+    let settings = stmt.query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    settings.collect()
+}
+
+pub fn set_setting(name: &str, value: &str) -> Result<()> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "INSERT INTO settings(name, value) VALUES (?1, ?2)
+        ON CONFLICT(name) DO UPDATE SET value = excluded.value",
+        params![name, value],
+    )?;
+
+    Ok(())
+}
+
+/// Removes setting `name`, returns false if it was not set
+pub fn unset_setting(name: &str) -> Result<bool> {
+    let conn = connect_db()?;
+
+    Ok(conn.execute("DELETE FROM settings WHERE name = ?1", params![name])? > 0)
+}
+
+/// Tasks of the project files of the directories of workspace
+/// `workspace_id` and the tasks added to it, ordered by name
+pub fn fetch_tasks(workspace_id: i32) -> Result<Vec<Task>> {
+    let conn = connect_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT t.name, t.dirId, t.command, t.depends, 0 FROM dir_tasks t
+        INNER JOIN dirs d ON d.id = t.dirId
+        WHERE d.workspaceId = ?1
+        UNION ALL
+        SELECT name, dirId, command, depends, 1 FROM workspace_tasks
+        WHERE workspaceId = ?1
+        ORDER BY 1, 5 DESC",
+    )?;
+
+    let tasks = stmt.query_map(params![workspace_id], |row| {
+        Ok(Task {
+            name: row.get(0)?,
+            dir_id: row.get(1)?,
+            command: row.get(2)?,
+            depends: tasks::split_depends(row.get(3)?),
+            origin: match row.get(4)? {
+                0 => Origin::Project,
+                _ => Origin::Workspace,
+            },
+        })
+    })?;
+
+    tasks.collect()
+}
+
+/// Adds `task` to workspace `workspace_id`, replacing a task with its name
+pub fn set_task(workspace_id: i32, task: &Task) -> Result<()> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO workspace_tasks(workspaceId, name, dirId, command, depends)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            workspace_id,
+            task.name,
+            task.dir_id,
+            task.command,
+            tasks::join_depends(&task.depends)
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Removes task `name` added to workspace `workspace_id`, returns false if
+/// there was none
+pub fn remove_task(workspace_id: i32, name: &str) -> Result<bool> {
+    let conn = connect_db()?;
+
+    Ok(conn.execute(
+        "DELETE FROM workspace_tasks WHERE workspaceId = ?1 AND name = ?2",
+        params![workspace_id, name],
+    )? > 0)
+}
+
+fn service_from_row(row: &Row) -> Result<Service> {
+    Ok(Service {
+        name: row.get("name")?,
+        dir_id: row.get("dirId")?,
+        command: row.get("command")?,
+        restart: row.get("restart")?,
+        pid: row.get("pid")?,
+        started: row.get("started")?,
+    })
+}
+
+/// Services of workspace `workspace_id` ordered by name
+pub fn fetch_services(workspace_id: i32) -> Result<Vec<Service>> {
+    let conn = connect_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT name, dirId, command, restart, pid, started FROM services
+        WHERE workspaceId = ?1 ORDER BY name",
+    )?;
+
+    let services = stmt.query_map(params![workspace_id], service_from_row)?;
+
+    services.collect()
+}
+
+/// Services of all workspaces which were started and not stopped since,
+/// with the name of their workspace
+pub fn fetch_started_services() -> Result<Vec<(String, Service)>> {
+    let conn = connect_db()?;
+    let mut stmt = conn.prepare(
+        "SELECT w.name AS workspace, s.name, s.dirId, s.command, s.restart, s.pid, s.started
+        FROM services s INNER JOIN workspaces w ON w.id = s.workspaceId
+        WHERE s.pid IS NOT NULL ORDER BY w.name, s.name",
+    )?;
+
+    let services = stmt.query_map(params![], |row| {
+        Ok((row.get("workspace")?, service_from_row(row)?))
+    })?;
+
+    services.collect()
+}
+
+/// Adds `service` to workspace `workspace_id`, a service with its name is
+/// changed and stays running
+pub fn set_service(workspace_id: i32, service: &Service) -> Result<()> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "INSERT INTO services(workspaceId, name, dirId, command, restart)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(workspaceId, name) DO UPDATE SET
+        dirId = excluded.dirId, command = excluded.command, restart = excluded.restart",
+        params![
+            workspace_id,
+            service.name,
+            service.dir_id,
+            service.command,
+            service.restart
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Records process group `pid` running service `name`, `None` once it stopped
+pub fn set_service_pid(workspace_id: i32, name: &str, pid: Option<i32>) -> Result<()> {
+    let conn = connect_db()?;
+
+    conn.execute(
+        "UPDATE services SET pid = ?3,
+        started = CASE WHEN ?3 IS NULL THEN NULL ELSE datetime('now') END
+        WHERE workspaceId = ?1 AND name = ?2",
+        params![workspace_id, name, pid],
+    )?;
+
+    Ok(())
+}
+
+/// Removes service `name` of workspace `workspace_id`, returns false if
+/// there was none
+pub fn remove_service(workspace_id: i32, name: &str) -> Result<bool> {
+    let conn = connect_db()?;
+
+    Ok(conn.execute(
+        "DELETE FROM services WHERE workspaceId = ?1 AND name = ?2",
+        params![workspace_id, name],
+    )? > 0)
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use std::path::Path;
+
+    use tempfile::TempDir;
+
+    use super::{initialize_db, insert_new_dir_for_workspace, insert_new_workspace, TEST_DB_PATH};
+    use crate::workspace::Workspace;
+
+    /// Points the database of the current test thread to a fresh file
+    /// The database lives as long as the returned directory
+    pub fn use_temp_db() -> TempDir {
+        let dir = tempfile::tempdir().expect("Error creating temp dir");
+        TEST_DB_PATH.with(|p| *p.borrow_mut() = Some(dir.path().join("workspaces.db")));
+        initialize_db().expect("Error initializing database");
+
+        dir
+    }
+
+    /// Inserts workspace `name` with `dirs` into the test database
+    pub fn insert_workspace<P: AsRef<Path>>(name: &str, dirs: &[P]) -> i32 {
+        let id = insert_new_workspace(Workspace::new(name.to_string())).unwrap() as i32;
+        for dir in dirs {
+            insert_new_dir_for_workspace(id, dir.as_ref()).unwrap();
+        }
+
+        id
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -368,6 +1665,7 @@ mod tests {
         let _ = fs::remove_file("./test.db");
     }
 
+    #[allow(clippy::useless_format)]
     fn connect_test_db() -> Result<Connection> {
         let path = format!("./test.db");
         let conn = Connection::open(path)?;
@@ -425,6 +1723,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::let_unit_value)]
     fn test_update_editor() -> Result<()> {
         let _conn = connect_db()?;
 
@@ -434,6 +1733,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_return)]
     fn insert_a_workspace() -> Result<()> {
         let conn = connect_test_db()?;
         let w = Workspace::new(String::from("test1"));
@@ -471,4 +1771,436 @@ mod tests {
             }
         }
     }
+
+    fn dir_paths(name: &str) -> Vec<String> {
+        let ws = fetch_workspace_with_dirs_by_name(name).expect("Workspace not found");
+        ws.dir_iter()
+            .map(|d| d.path.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn should_rename_workspace_keeping_dirs() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("old", &["/a", "/b"]);
+        update_workspace_editor("old", Some(String::from("vim"))).unwrap();
+
+        rename_workspace("old", "new").unwrap();
+
+        assert!(!workspace_exists("old").unwrap());
+        assert_eq!(dir_paths("new"), vec!["/a", "/b"]);
+        let ws = fetch_workspace_with_dirs_by_name("new").unwrap();
+        assert_eq!(ws.editor.as_deref(), Some("vim"));
+    }
+
+    #[test]
+    fn should_copy_workspace_with_editor() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("src", &["/a", "/b"]);
+        update_workspace_editor("src", Some(String::from("vim"))).unwrap();
+
+        copy_workspace("src", "dst").unwrap();
+
+        assert_eq!(dir_paths("src"), dir_paths("dst"));
+        let ws = fetch_workspace_with_dirs_by_name("dst").unwrap();
+        assert_eq!(ws.editor.as_deref(), Some("vim"));
+    }
+
+    #[test]
+    fn should_merge_workspaces_without_duplicates() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("a", &["/a", "/shared"]);
+        test_support::insert_workspace("b", &["/shared", "/b"]);
+
+        let count = merge_workspaces(&[String::from("a"), String::from("b")], "c").unwrap();
+
+        assert_eq!(count, 3);
+        assert_eq!(dir_paths("c"), vec!["/a", "/shared", "/b"]);
+        assert!(!workspace_exists("a").unwrap());
+        assert!(!workspace_exists("b").unwrap());
+    }
+
+    #[test]
+    fn should_merge_into_one_of_the_sources() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("a", &["/a"]);
+        test_support::insert_workspace("b", &["/a", "/b"]);
+
+        merge_workspaces(&[String::from("a"), String::from("b")], "a").unwrap();
+
+        assert_eq!(dir_paths("a"), vec!["/a", "/b"]);
+        assert!(!workspace_exists("b").unwrap());
+    }
+
+    #[test]
+    fn should_split_dirs_into_new_workspace() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/a", "/b", "/c"]);
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        let ids: Vec<i32> = ws
+            .dir_iter()
+            .filter(|d| d.path != Path::new("/a"))
+            .map(|d| d.id)
+            .collect();
+
+        split_workspace("x", &ids, "y").unwrap();
+
+        assert_eq!(dir_paths("x"), vec!["/a"]);
+        assert_eq!(dir_paths("y"), vec!["/b", "/c"]);
+    }
+
+    #[test]
+    fn failed_split_should_leave_workspaces_untouched() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/a", "/b"]);
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        let id = ws.find_dir("/b").unwrap().id;
+
+        assert!(split_workspace("x", &[id, 9999], "y").is_err());
+
+        assert_eq!(dir_paths("x"), vec!["/a", "/b"]);
+        assert!(!workspace_exists("y").unwrap());
+    }
+
+    #[test]
+    fn should_order_dirs_by_position() {
+        let _db = test_support::use_temp_db();
+        let id = test_support::insert_workspace("x", &["/a", "/b", "/c"]);
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        let ids: Vec<i32> = ws.dir_iter().map(|d| d.id).collect();
+
+        reorder_dirs(id, &[ids[2], ids[0], ids[1]]).unwrap();
+
+        assert_eq!(dir_paths("x"), vec!["/c", "/a", "/b"]);
+    }
+
+    #[test]
+    fn should_keep_single_primary_and_unique_labels_when_merging() {
+        let _db = test_support::use_temp_db();
+        let a = test_support::insert_workspace("a", &["/a"]);
+        let b = test_support::insert_workspace("b", &["/b"]);
+        for (workspace_id, name) in [(a, "a"), (b, "b")] {
+            let ws = fetch_workspace_with_dirs_by_name(name).unwrap();
+            let dir_id = ws.primary_dir().unwrap().id;
+            update_dir_label(dir_id, Some(String::from("api"))).unwrap();
+            set_primary_dir(workspace_id, dir_id).unwrap();
+        }
+
+        merge_workspaces(&[String::from("a"), String::from("b")], "c").unwrap();
+
+        let ws = fetch_workspace_with_dirs_by_name("c").unwrap();
+        assert_eq!(ws.find_dir("api").unwrap().path, Path::new("/a"));
+        assert_eq!(ws.primary_dir().unwrap().path, Path::new("/a"));
+        assert_eq!(ws.dir_iter().filter(|d| d.primary).count(), 1);
+        assert_eq!(ws.dir_iter().filter(|d| d.label.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn should_create_workspace_with_dirs_at_once() {
+        let _db = test_support::use_temp_db();
+        let mut ws = Workspace::new(String::from("x"));
+        ws.editor = Some(String::from("vim"));
+        for (path, label) in [("/b", "api"), ("/a", "api"), ("/b", "web")] {
+            let mut dir = Dir::new(path);
+            dir.label = Some(String::from(label));
+            ws.add_dir(dir);
+        }
+
+        create_workspace(&ws).unwrap();
+
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        assert_eq!(dir_paths("x"), vec!["/b", "/a"]);
+        assert_eq!(ws.editor.as_deref(), Some("vim"));
+        assert_eq!(ws.find_dir("api").unwrap().path, Path::new("/b"));
+        assert_eq!(ws.dir_iter().filter(|d| d.label.is_some()).count(), 1);
+        assert!(create_workspace(&ws).is_err());
+    }
+
+    #[test]
+    fn should_update_workspace_keeping_dir_ids() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/a", "/b", "/c"]);
+        test_support::insert_workspace("y", &["/y"]);
+        let before = fetch_workspace_with_dirs_by_name("x").unwrap();
+        let id_of = |ws: &Workspace, path: &str| ws.find_dir(path).unwrap().id;
+
+        let mut desired = Workspace::new(String::from("x"));
+        desired.env.insert(String::from("K"), String::from("v"));
+        for path in ["/c", "/d", "/a"] {
+            desired.add_dir(Dir::new(path));
+        }
+        write_workspaces(&[
+            WorkspaceWrite::Update(&desired),
+            WorkspaceWrite::Delete("y"),
+        ])
+        .unwrap();
+
+        let after = fetch_workspace_with_dirs_by_name("x").unwrap();
+        assert_eq!(dir_paths("x"), vec!["/c", "/d", "/a"]);
+        assert_eq!(id_of(&after, "/a"), id_of(&before, "/a"));
+        assert_eq!(id_of(&after, "/c"), id_of(&before, "/c"));
+        assert_eq!(after.env.get("K").map(String::as_str), Some("v"));
+        assert!(!workspace_exists("y").unwrap());
+    }
+
+    #[test]
+    fn should_replace_what_the_old_project_file_set() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/a"]);
+        let dir_id = fetch_workspace_with_dirs_by_name("x")
+            .unwrap()
+            .dir_iter()
+            .next()
+            .unwrap()
+            .id;
+        let tasks = |dir_id: i32| -> Vec<String> {
+            let conn = connect_db().unwrap();
+            let mut stmt = conn
+                .prepare("SELECT name FROM dir_tasks WHERE dirId = ?1")
+                .unwrap();
+            let names = stmt.query_map([dir_id], |row| row.get(0)).unwrap();
+            names.map(|name| name.unwrap()).collect()
+        };
+
+        let old_text = "label = \"api\"\nscript = \"make\"\n[env]\nA = \"1\"\nB = \"2\"\n[tasks]\nbuild = \"make\"";
+        let old = ProjectFile::parse(old_text).unwrap();
+        apply_project(dir_id, None, Some((old_text, &old))).unwrap();
+
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        assert_eq!(ws.find_dir("api").unwrap().init.as_deref(), Some("make"));
+        assert_eq!(ws.env.len(), 2);
+        assert_eq!(tasks(dir_id), vec!["build"]);
+
+        let new_text = "[env]\nA = \"3\"";
+        let new = ProjectFile::parse(new_text).unwrap();
+        apply_project(dir_id, Some(&old), Some((new_text, &new))).unwrap();
+
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        let dir = ws.dir_iter().next().unwrap();
+        assert_eq!((dir.label.as_deref(), dir.init.as_deref()), (None, None));
+        assert_eq!(dir.project.as_deref(), Some(new_text));
+        assert_eq!(
+            ws.env.iter().collect::<Vec<_>>(),
+            vec![(&String::from("A"), &String::from("3"))]
+        );
+        assert!(tasks(dir_id).is_empty());
+    }
+
+    #[test]
+    fn should_migrate_initial_schema() {
+        let dir = test_support::use_temp_db();
+        let path = dir.path().join("old.db");
+        TEST_DB_PATH.with(|p| *p.borrow_mut() = Some(path));
+        let conn = connect_db().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE editor (id INTEGER PRIMARY KEY AUTOINCREMENT, editor TEXT NOT NULL);
+            CREATE TABLE workspaces (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE NOT NULL);
+            CREATE TABLE dirs (id INTEGER PRIMARY KEY AUTOINCREMENT, workspaceId INTEGER,
+                path TEXT NOT NULL, script TEXT);
+            INSERT INTO workspaces(name) VALUES ('old');
+            INSERT INTO dirs(workspaceId, path) VALUES (1, '/b'), (1, '/a'), (1, '/b'), (2, '/c');",
+        )
+        .unwrap();
+
+        initialize_db().unwrap();
+
+        assert_eq!(dir_paths("old"), vec!["/b", "/a"]);
+        let version: usize = conn
+            .query_row("PRAGMA user_version", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn should_reject_duplicate_dir() {
+        let _db = test_support::use_temp_db();
+        let id = test_support::insert_workspace("x", &["/a"]);
+
+        let err = insert_new_dir_for_workspace(id, Path::new("/a")).unwrap_err();
+
+        assert!(matches!(
+            crate::errors::Error::from(err),
+            crate::errors::Error::AlreadyExists(_)
+        ));
+        assert_eq!(dir_paths("x"), vec!["/a"]);
+
+        // a missing workspace is no duplicate
+        let err = insert_new_dir_for_workspace(id + 1, Path::new("/b")).unwrap_err();
+        assert!(matches!(
+            crate::errors::Error::from(err),
+            crate::errors::Error::DbError(_)
+        ));
+    }
+
+    #[test]
+    fn should_delete_dirs_with_workspace() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/a"]);
+
+        delete_workspace(String::from("x")).unwrap();
+
+        assert!(get_dirs_for_workspace(1).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_store_non_utf8_paths() {
+        let _db = test_support::use_temp_db();
+        let path = utils::bytes_to_path(b"/tmp/caf\xe9".to_vec());
+        test_support::insert_workspace("x", &[&path]);
+
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+
+        assert_eq!(ws.primary_dir().unwrap().path, path);
+    }
+
+    #[test]
+    fn should_store_dirs_relative_to_roots() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/mnt/code/api", "/srv/web"]);
+        let stored = |id: i32| -> Vec<u8> {
+            let conn = connect_db().unwrap();
+            conn.query_row("SELECT path FROM dirs WHERE id = ?1", params![id], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+
+        upsert_root("code", Path::new("/mnt/code")).unwrap();
+        assert_eq!(stored(1), b"@code/api".to_vec());
+        assert_eq!(stored(2), b"/srv/web".to_vec());
+
+        upsert_root("code", Path::new("/home/bob/code")).unwrap();
+        assert_eq!(dir_paths("x"), vec!["/home/bob/code/api", "/srv/web"]);
+
+        assert!(remove_root("code").unwrap());
+        assert_eq!(dir_paths("x"), vec!["/home/bob/code/api", "/srv/web"]);
+        assert!(!remove_root("code").unwrap());
+    }
+
+    #[test]
+    fn save_and_delete_snapshots() {
+        let _db = test_support::use_temp_db();
+        let id = test_support::insert_workspace("x", &["/srv/api", "/srv/web"]);
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+
+        let repos: Vec<RepoState> = ws
+            .dir_iter()
+            .map(|dir| RepoState {
+                dir_id: dir.id,
+                path: dir.path.clone(),
+                head: String::from("1a2b3c4d"),
+                branch: Some(String::from("main")),
+                dirty: false,
+            })
+            .collect();
+
+        assert_eq!(save_snapshot(id, Some("before"), &repos).unwrap(), "before");
+        assert!(save_snapshot(id, Some("before"), &repos).is_err());
+        let generated = save_snapshot(id, None, &repos[..1]).unwrap();
+        assert!(generated.starts_with("20"));
+
+        let snapshots = fetch_snapshots(Some("x")).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].repos, repos);
+
+        assert!(delete_snapshot("x", "before").unwrap());
+        assert!(!delete_snapshot("x", "before").unwrap());
+        assert_eq!(fetch_snapshots(None).unwrap()[0].name, generated);
+    }
+
+    #[test]
+    fn stores_tasks_and_copies_them_along() {
+        let _db = test_support::use_temp_db();
+        let id = test_support::insert_workspace("x", &["/srv/api", "/srv/web"]);
+        let ws = fetch_workspace_with_dirs_by_name("x").unwrap();
+        let web = ws.dir_iter().nth(1).unwrap().id;
+
+        let task = |name: &str, dir_id: Option<i32>, depends: &[&str]| Task {
+            name: String::from(name),
+            dir_id,
+            command: format!("make {}", name),
+            depends: depends.iter().map(|dep| String::from(*dep)).collect(),
+            origin: Origin::Workspace,
+        };
+        set_task(id, &task("lint", None, &[])).unwrap();
+        set_task(id, &task("build", Some(web), &["lint", "api:build"])).unwrap();
+        set_task(id, &task("build", Some(web), &["lint"])).unwrap();
+
+        assert_eq!(
+            fetch_tasks(id).unwrap(),
+            vec![task("build", Some(web), &["lint"]), task("lint", None, &[])]
+        );
+
+        let copy = copy_workspace("x", "y").unwrap();
+        let copied = fetch_tasks(copy).unwrap();
+        assert_eq!(copied.len(), 2);
+        assert_ne!(copied[0].dir_id, Some(web));
+
+        assert!(remove_task(id, "lint").unwrap());
+        assert!(!remove_task(id, "lint").unwrap());
+        assert_eq!(fetch_tasks(id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn records_services_and_their_processes() {
+        let _db = test_support::use_temp_db();
+        let id = test_support::insert_workspace("x", &["/srv/api"]);
+
+        let mut service = Service {
+            name: String::from("web"),
+            dir_id: None,
+            command: String::from("npm run dev"),
+            restart: false,
+            pid: None,
+            started: None,
+        };
+        set_service(id, &service).unwrap();
+        set_service_pid(id, "web", Some(4242)).unwrap();
+
+        service.restart = true;
+        set_service(id, &service).unwrap();
+        let stored = fetch_services(id).unwrap();
+        assert_eq!(stored[0].pid, Some(4242));
+        assert!(stored[0].restart && stored[0].started.is_some());
+        assert_eq!(fetch_started_services().unwrap()[0].0, "x");
+
+        set_service_pid(id, "web", None).unwrap();
+        assert!(fetch_started_services().unwrap().is_empty());
+        assert!(remove_service(id, "web").unwrap());
+        assert!(fetch_services(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn set_and_unset_settings() {
+        let _db = test_support::use_temp_db();
+
+        assert_eq!(get_setting("worktrees").unwrap(), None);
+        set_setting("worktrees", "~/wt").unwrap();
+        set_setting("worktrees", "~/worktrees").unwrap();
+        assert_eq!(
+            fetch_settings().unwrap(),
+            vec![(String::from("worktrees"), String::from("~/worktrees"))]
+        );
+
+        assert!(unset_setting("worktrees").unwrap());
+        assert!(!unset_setting("worktrees").unwrap());
+        assert_eq!(get_setting("worktrees").unwrap(), None);
+    }
+
+    #[test]
+    fn failed_path_update_should_change_nothing() {
+        let _db = test_support::use_temp_db();
+        test_support::insert_workspace("x", &["/old/api", "/old/web", "/new/web"]);
+
+        let changes = vec![
+            (1, PathBuf::from("/new/api")),
+            (2, PathBuf::from("/new/web")),
+        ];
+        assert!(update_dir_paths(&changes).is_err());
+        assert_eq!(dir_paths("x"), vec!["/old/api", "/old/web", "/new/web"]);
+
+        update_dir_paths(&changes[..1]).unwrap();
+        assert_eq!(dir_paths("x"), vec!["/new/api", "/old/web", "/new/web"]);
+    }
 }
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git::{self, GitIdentity};
+use crate::utils;
+use crate::workspace::Workspace;
+
+/// How deep below a root repositories are searched for
+const SEARCH_DEPTH: usize = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by [`check_workspaces`] or [`check_binaries`]
+#[derive(Debug)]
+pub struct Finding {
+    /// Workspace the problem belongs to, `None` for global problems
+    pub workspace: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+    /// Set for missing git directories which could be found again
+    pub missing_repo: Option<MissingRepo>,
+}
+
+/// A missing directory which was a git repository
+#[derive(Debug)]
+pub struct MissingRepo {
+    pub dir_id: i32,
+    pub path: PathBuf,
+    pub identity: GitIdentity,
+}
+
+impl Finding {
+    fn new(workspace: Option<&str>, severity: Severity, message: String) -> Self {
+        Finding {
+            workspace: workspace.map(String::from),
+            severity,
+            message,
+            missing_repo: None,
+        }
+    }
+}
+
+/// Checks the directories of all `spaces`
+pub fn check_workspaces(spaces: &[Workspace]) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut owners: HashMap<&Path, Vec<&str>> = HashMap::new();
+
+    for space in spaces {
+        let name = Some(space.name.as_str());
+
+        if space.dir_iter().next().is_none() {
+            findings.push(Finding::new(
+                name,
+                Severity::Warning,
+                String::from("Workspace has no directories"),
+            ));
+        }
+
+        for dir in space.dir_iter() {
+            owners.entry(&dir.path).or_default().push(&space.name);
+
+            if !dir.path.exists() {
+                let mut finding = Finding::new(
+                    name,
+                    Severity::Error,
+                    format!("{} does not exist", dir.path.display()),
+                );
+                finding.missing_repo = dir.git.clone().map(|identity| MissingRepo {
+                    dir_id: dir.id,
+                    path: dir.path.clone(),
+                    identity,
+                });
+                findings.push(finding);
+            } else if let Err(err) = fs::read_dir(&dir.path) {
+                findings.push(Finding::new(
+                    name,
+                    Severity::Error,
+                    format!("{} is not readable: {}", dir.path.display(), err),
+                ));
+            }
+
+            if let Some(parent) = space
+                .dir_iter()
+                .find(|other| other.id != dir.id && dir.path.starts_with(&other.path))
+            {
+                findings.push(Finding::new(
+                    name,
+                    Severity::Warning,
+                    format!(
+                        "{} is nested inside {}",
+                        dir.path.display(),
+                        parent.path.display()
+                    ),
+                ));
+            }
+        }
+    }
+
+    let mut duplicates: Vec<(&Path, Vec<&str>)> = owners
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .collect();
+    duplicates.sort();
+
+    for (path, names) in duplicates {
+        findings.push(Finding::new(
+            None,
+            Severity::Warning,
+            format!(
+                "{} is part of several workspaces: {}",
+                path.display(),
+                names.join(", ")
+            ),
+        ));
+    }
+
+    findings
+}
+
+/// Checks that the configured programs, given as `(what, command)`, can be found
+pub fn check_binaries(commands: &[(String, String)]) -> Vec<Finding> {
+    commands
+        .iter()
+        .filter_map(|(what, command)| {
+            let binary = command.split_whitespace().next()?;
+            match utils::find_in_path(binary) {
+                Some(_) => None,
+                None => Some(Finding::new(
+                    None,
+                    Severity::Error,
+                    format!("{} `{}` cannot be found", what, binary),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Finds all git repositories below `roots`
+pub fn find_repositories(roots: &[PathBuf]) -> Vec<(PathBuf, GitIdentity)> {
+    let mut found = vec![];
+
+    for root in roots {
+        collect_repositories(root, SEARCH_DEPTH, &mut found);
+    }
+
+    found
+        .into_iter()
+        .filter_map(|path| git::identity(&path).map(|identity| (path, identity)))
+        .collect()
+}
+
+fn collect_repositories(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    if git::is_repo(dir) {
+        found.push(dir.to_path_buf());
+        return;
+    }
+
+    if depth == 0 {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut children: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    children.sort();
+
+    for child in children {
+        collect_repositories(&child, depth - 1, found);
+    }
+}
+
+/// Repositories of `candidates` which are the same as the missing one
+pub fn find_moved_repository<'a>(
+    missing: &MissingRepo,
+    candidates: &'a [(PathBuf, GitIdentity)],
+) -> Vec<&'a Path> {
+    candidates
+        .iter()
+        .filter(|(_, identity)| identity.matches(&missing.identity))
+        .map(|(path, _)| path.as_path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::git::test_support::init_repo;
+    use crate::workspace::Dir;
+
+    fn workspace(name: &str, dirs: &[&Path]) -> Workspace {
+        let mut ws = Workspace::new(String::from(name));
+        for (i, path) in dirs.iter().enumerate() {
+            let mut dir = Dir::new(*path);
+            dir.id = i as i32 + 1;
+            ws.add_dir(dir);
+        }
+        ws
+    }
+
+    #[test]
+    fn reports_missing_nested_duplicate_and_empty() {
+        let root = tempfile::tempdir().unwrap();
+        let api = root.path().join("api");
+        let nested = api.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        let missing = root.path().join("missing");
+
+        let spaces = vec![
+            workspace("a", &[&api, &nested, &missing]),
+            workspace("b", &[&api]),
+            workspace("c", &[]),
+        ];
+        let messages: Vec<String> = check_workspaces(&spaces)
+            .into_iter()
+            .map(|f| f.message)
+            .collect();
+
+        assert_eq!(messages.len(), 4);
+        assert!(messages[0].ends_with(&format!("is nested inside {}", api.display())));
+        assert!(messages[1].ends_with("does not exist"));
+        assert_eq!(messages[2], "Workspace has no directories");
+        assert!(messages[3].ends_with("is part of several workspaces: a, b"));
+    }
+
+    #[test]
+    fn finds_moved_repository_by_identity() {
+        let root = tempfile::tempdir().unwrap();
+        let moved = root.path().join("code").join("api");
+        init_repo(&moved);
+        init_repo(&root.path().join("code").join("web"));
+
+        let missing = MissingRepo {
+            dir_id: 1,
+            path: PathBuf::from("/gone/api"),
+            identity: git::identity(&moved).unwrap(),
+        };
+        let candidates = find_repositories(&[root.path().to_path_buf()]);
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(find_moved_repository(&missing, &candidates), vec![moved]);
+    }
+
+    #[test]
+    fn reports_unknown_binaries() {
+        let findings = check_binaries(&[
+            (String::from("Shell"), String::from("sh -l")),
+            (
+                String::from("Editor"),
+                String::from("no-such-editor-ws --wait"),
+            ),
+        ]);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "Editor `no-such-editor-ws` cannot be found"
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Error;
+use crate::git::GitIdentity;
+use crate::roots::{self, Root};
+use crate::workspace::{Dir, Workspace};
+
+/// Version of the document schema written by this build,
+/// older documents are still read
+pub const VERSION: u32 = 1;
+
+/// File format of a [`Document`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            _ => Err(format!("Unknown format {}, use json or toml", s)),
+        }
+    }
+}
+
+impl Format {
+    /// Format of `file` by its extension, json unless it ends in `.toml`
+    pub fn of_file(file: &Path) -> Format {
+        match file.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// Workspace definitions as they are exported and imported
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub version: u32,
+    #[serde(default)]
+    pub workspaces: Vec<WorkspaceDef>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceDef {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub dirs: Vec<DirDef>,
+}
+
+/// A directory, its path is relative to `~` or a root (`@code/api`) when possible
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DirDef {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub primary: bool,
+    /// Git remote the directory can be cloned from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Branch checked out after cloning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Document {
+    /// Describes `spaces` with paths made portable using `roots` and `home`
+    pub fn new(spaces: &[Workspace], roots: &[Root], home: Option<&Path>) -> Document {
+        let workspaces = spaces
+            .iter()
+            .map(|ws| WorkspaceDef {
+                name: ws.name.clone(),
+                editor: ws.editor.clone(),
+                env: ws.env.clone(),
+                dirs: ws
+                    .dir_iter()
+                    .map(|dir| DirDef {
+                        path: roots::to_portable(&dir.path, roots, home)
+                            .to_string_lossy()
+                            .into_owned(),
+                        label: dir.label.clone(),
+                        script: dir.init.clone(),
+                        primary: dir.primary,
+                        remote: dir.git.as_ref().and_then(|git| git.remote.clone()),
+                        branch: dir.git.as_ref().and_then(|git| git.default_branch.clone()),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Document {
+            version: VERSION,
+            workspaces,
+        }
+    }
+
+    pub fn parse(text: &str, format: Format) -> Result<Document, Error> {
+        let document: Document = match format {
+            Format::Json => serde_json::from_str(text).map_err(|err| err.to_string()),
+            Format::Toml => toml::from_str(text).map_err(|err| err.to_string()),
+        }
+        .map_err(|err| Error::InvalidFile(format!("Invalid document: {}", err)))?;
+
+        if document.version > VERSION {
+            return Err(Error::InvalidFile(format!(
+                "Document version {} is newer than the supported version {}",
+                document.version, VERSION
+            )));
+        }
+
+        Ok(document)
+    }
+
+    pub fn to_string(&self, format: Format) -> String {
+        // documents only contain strings, maps and lists which always serialize
+        match format {
+            Format::Json => serde_json::to_string_pretty(self).unwrap(),
+            Format::Toml => toml::to_string(self).unwrap(),
+        }
+    }
+
+    /// Turns the definitions into workspaces with absolute paths,
+    /// failing on unknown roots, relative paths, duplicate names and remotes
+    /// git would take for an option
+    pub fn into_workspaces(
+        self,
+        roots: &[Root],
+        home: Option<&Path>,
+    ) -> Result<Vec<Workspace>, Error> {
+        let mut spaces: Vec<Workspace> = vec![];
+
+        for def in self.workspaces {
+            if spaces.iter().any(|ws| ws.name == def.name) {
+                return Err(Error::InvalidFile(format!(
+                    "Workspace {} is defined twice",
+                    def.name
+                )));
+            }
+
+            let mut ws = Workspace::new(def.name);
+            ws.editor = def.editor;
+            ws.env = def.env;
+
+            for dir_def in def.dirs {
+                let path = roots::from_portable(Path::new(&dir_def.path), roots, home);
+                if !path.is_absolute() {
+                    return Err(Error::InvalidFile(format!(
+                        "Directory {} of workspace {} is not absolute or uses an unknown root",
+                        dir_def.path, ws.name
+                    )));
+                }
+
+                if let Some(remote) = dir_def.remote.as_ref().filter(|r| r.starts_with('-')) {
+                    return Err(Error::InvalidFile(format!(
+                        "Remote {} of directory {} is not a valid git remote",
+                        remote, dir_def.path
+                    )));
+                }
+
+                let mut dir = Dir::new(path);
+                dir.label = dir_def.label;
+                dir.init = dir_def.script;
+                dir.primary = dir_def.primary;
+                if dir_def.remote.is_some() {
+                    dir.git = Some(GitIdentity {
+                        remote: dir_def.remote,
+                        default_branch: dir_def.branch,
+                        ..Default::default()
+                    });
+                }
+                ws.add_dir(dir);
+            }
+
+            spaces.push(ws);
+        }
+
+        Ok(spaces)
+    }
+}
+
+/// What to do with an imported workspace whose name is already taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    Skip,
+    Overwrite,
+    Merge,
+    Rename,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Strategy::Skip),
+            "overwrite" => Ok(Strategy::Overwrite),
+            "merge" => Ok(Strategy::Merge),
+            "rename" => Ok(Strategy::Rename),
+            _ => Err(format!(
+                "Unknown strategy {}, use skip, overwrite, merge or rename",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Create,
+    Overwrite,
+    Merge,
+    /// Name is taken and the strategy is to skip
+    Conflict,
+    Unchanged,
+}
+
+/// Planned import of one workspace
+#[derive(Debug)]
+pub struct Change {
+    pub action: Action,
+    pub workspace: Workspace,
+    /// Short explanation shown in the preview
+    pub detail: String,
+}
+
+/// Decides for every imported workspace what happens to it
+pub fn plan_import(
+    imported: Vec<Workspace>,
+    existing: &[Workspace],
+    strategy: Strategy,
+) -> Vec<Change> {
+    let mut taken: Vec<String> = existing.iter().map(|ws| ws.name.clone()).collect();
+    let mut changes = vec![];
+
+    for mut ws in imported {
+        let dir_count = format!("{} directories", ws.dir_iter().count());
+        let current = existing.iter().find(|current| current.name == ws.name);
+
+        let (action, detail) = match (current, strategy) {
+            (None, _) => (Action::Create, dir_count),
+            (Some(current), _) if same_definition(current, &ws) => {
+                (Action::Unchanged, String::new())
+            }
+            (Some(_), Strategy::Skip) => (Action::Conflict, String::from("already exists")),
+            (Some(_), Strategy::Overwrite) => (Action::Overwrite, dir_count),
+            (Some(current), Strategy::Merge) => {
+                let dirs = ws
+                    .dir_iter()
+                    .filter(|dir| current.check_dir_already_exists(&dir.path).is_none())
+                    .count();
+                let env = ws
+                    .env
+                    .keys()
+                    .filter(|name| !current.env.contains_key(*name))
+                    .count();
+                let editor = current.editor.is_none() && ws.editor.is_some();
+
+                match (dirs, env, editor) {
+                    (0, 0, false) => (Action::Unchanged, String::new()),
+                    _ => (
+                        Action::Merge,
+                        format!("+{} directories, +{} variables", dirs, env),
+                    ),
+                }
+            }
+            (Some(_), Strategy::Rename) => {
+                let name = (2..)
+                    .map(|i| format!("{}-{}", ws.name, i))
+                    .find(|name| !taken.contains(name))
+                    .unwrap();
+                let detail = format!("renamed from {}, {}", ws.name, dir_count);
+                ws.name = name;
+                (Action::Create, detail)
+            }
+        };
+
+        taken.push(ws.name.clone());
+        changes.push(Change {
+            action,
+            workspace: ws,
+            detail,
+        });
+    }
+
+    changes
+}
+
+/// Checks if both workspaces have the same editor, env and directories
+fn same_definition(a: &Workspace, b: &Workspace) -> bool {
+    let dirs = |ws: &Workspace| -> Vec<(PathBuf, Option<String>, Option<String>, bool)> {
+        ws.dir_iter()
+            .map(|d| (d.path.clone(), d.label.clone(), d.init.clone(), d.primary))
+            .collect()
+    };
+
+    a.editor == b.editor && a.env == b.env && dirs(a) == dirs(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Workspace {
+        let mut ws = Workspace::new(String::from("team"));
+        ws.editor = Some(String::from("vim"));
+        ws.env
+            .insert(String::from("RUST_LOG"), String::from("debug"));
+
+        let mut api = Dir::new("/home/alice/code/api");
+        api.label = Some(String::from("api"));
+        api.init = Some(String::from("cargo build"));
+        api.primary = true;
+        ws.add_dir(api);
+        ws.add_dir(Dir::new("/srv/web"));
+
+        ws
+    }
+
+    fn roots() -> Vec<Root> {
+        vec![Root {
+            name: String::from("code"),
+            path: PathBuf::from("/home/alice/code"),
+        }]
+    }
+
+    #[test]
+    fn round_trips_json_and_toml() {
+        let home = Some(Path::new("/home/alice"));
+        let document = Document::new(&[sample()], &roots(), home);
+        assert_eq!(document.workspaces[0].dirs[0].path, "@code/api");
+
+        for format in [Format::Json, Format::Toml] {
+            let text = document.to_string(format);
+            let parsed = Document::parse(&text, format).unwrap();
+            assert_eq!(parsed, document);
+
+            let spaces = parsed.into_workspaces(&roots(), home).unwrap();
+            assert!(same_definition(&spaces[0], &sample()));
+        }
+    }
+
+    #[test]
+    fn rejects_newer_versions_and_unknown_roots() {
+        let newer = r#"{ "version": 99, "workspaces": [] }"#;
+        assert!(Document::parse(newer, Format::Json).is_err());
+
+        let unknown = r#"{ "version": 1, "workspaces": [{ "name": "a", "dirs": [{ "path": "@work/api" }] }] }"#;
+        let document = Document::parse(unknown, Format::Json).unwrap();
+        assert!(document.into_workspaces(&roots(), None).is_err());
+
+        let option = r#"{ "version": 1, "workspaces": [{ "name": "a", "dirs": [{ "path": "/api", "remote": "--upload-pack=touch x" }] }] }"#;
+        let document = Document::parse(option, Format::Json).unwrap();
+        assert!(document.into_workspaces(&roots(), None).is_err());
+    }
+
+    #[test]
+    fn plans_import_by_strategy() {
+        let existing = [sample(), Workspace::new(String::from("team-2"))];
+
+        let plan = |strategy| {
+            let mut changed = sample();
+            changed.add_dir(Dir::new("/srv/docs"));
+            let mut new = Workspace::new(String::from("new"));
+            new.add_dir(Dir::new("/srv/new"));
+
+            plan_import(vec![sample(), changed, new], &existing, strategy)
+                .into_iter()
+                .map(|c| (c.action, c.workspace.name))
+                .collect::<Vec<_>>()
+        };
+        let team = |action| (action, String::from("team"));
+
+        assert_eq!(
+            plan(Strategy::Skip),
+            [
+                team(Action::Unchanged),
+                team(Action::Conflict),
+                (Action::Create, String::from("new"))
+            ]
+        );
+        assert_eq!(plan(Strategy::Merge)[1], team(Action::Merge));
+        assert_eq!(plan(Strategy::Overwrite)[1], team(Action::Overwrite));
+        assert_eq!(
+            plan(Strategy::Rename)[1],
+            (Action::Create, String::from("team-3"))
+        );
+    }
+}
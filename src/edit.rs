@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use crate::document::{Document, Format};
+use crate::roots::Root;
+use crate::workspace::Workspace;
+
+/// First line of the comments `ws edit` puts above the definition
+const HEADER: &str = "# ws edit:";
+
+/// Text the user edits for workspace `ws`
+pub fn to_text(ws: &Workspace, roots: &[Root], home: Option<&Path>) -> String {
+    let document = Document::new(std::slice::from_ref(ws), roots, home);
+
+    format!(
+        "{} change the workspace and save, an empty file cancels\n{}",
+        HEADER,
+        document.to_string(Format::Toml)
+    )
+}
+
+/// `text` with the errors of the last attempt as comments on top
+pub fn with_errors(text: &str, errors: &[String]) -> String {
+    let mut out = format!(
+        "{} the file is invalid, fix it and save, an empty file cancels\n",
+        HEADER
+    );
+    for error in errors {
+        for line in error.lines() {
+            out.push_str(&format!("# {}\n", line));
+        }
+    }
+
+    out + &strip_comments(text)
+}
+
+/// Removes the comments added by [`to_text`] and [`with_errors`]
+pub fn strip_comments(text: &str) -> String {
+    let mut lines = text.lines().peekable();
+
+    if lines.peek().is_some_and(|line| line.starts_with(HEADER)) {
+        lines.next();
+        while lines.peek().is_some_and(|line| line.starts_with('#')) {
+            lines.next();
+        }
+    }
+
+    lines.map(|line| format!("{}\n", line)).collect()
+}
+
+/// Checks the edited definition of workspace `name`,
+/// returns the workspace it describes or all problems found
+pub fn validate(
+    text: &str,
+    name: &str,
+    roots: &[Root],
+    home: Option<&Path>,
+) -> Result<Workspace, Vec<String>> {
+    let document = Document::parse(text, Format::Toml).map_err(|err| vec![err.to_string()])?;
+    let mut spaces = document
+        .into_workspaces(roots, home)
+        .map_err(|err| vec![err.to_string()])?;
+
+    if spaces.len() != 1 {
+        return Err(vec![String::from(
+            "Exactly one workspace has to be defined",
+        )]);
+    }
+    let ws = spaces.remove(0);
+
+    let mut errors = vec![];
+    if ws.name != name {
+        errors.push(format!(
+            "The workspace cannot be renamed here, use `ws rename {} {}`",
+            name, ws.name
+        ));
+    }
+
+    for (i, dir) in ws.dir_iter().enumerate() {
+        if ws.dir_iter().take(i).any(|other| other.path == dir.path) {
+            errors.push(format!("Directory {} is listed twice", dir.path.display()));
+        }
+
+        if let Some(label) = &dir.label {
+            if label.is_empty() || label.contains('/') {
+                errors.push(format!("Invalid label {}", label));
+            } else if ws
+                .dir_iter()
+                .take(i)
+                .any(|other| other.label.as_ref() == Some(label))
+            {
+                errors.push(format!("Label {} is used twice", label));
+            }
+        }
+    }
+
+    if ws.dir_iter().filter(|dir| dir.primary).count() > 1 {
+        errors.push(String::from("Only one directory can be primary"));
+    }
+
+    for env_name in ws.env.keys() {
+        if env_name.is_empty() || env_name.contains('=') {
+            errors.push(format!("Invalid environment variable name {}", env_name));
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(ws),
+        false => Err(errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::Dir;
+
+    fn sample() -> Workspace {
+        let mut ws = Workspace::new(String::from("x"));
+        let mut dir = Dir::new("/srv/api");
+        dir.label = Some(String::from("api"));
+        ws.add_dir(dir);
+        ws
+    }
+
+    #[test]
+    fn round_trips_unchanged_text() {
+        let text = to_text(&sample(), &[], None);
+        let ws = validate(&strip_comments(&text), "x", &[], None).unwrap();
+
+        assert_eq!(ws.find_dir("api").unwrap().path, Path::new("/srv/api"));
+    }
+
+    #[test]
+    fn reports_all_errors_as_comments() {
+        let text = to_text(&sample(), &[], None)
+            .replace("name = \"x\"", "name = \"y\"")
+            .replace("path = \"/srv/api\"", "path = \"/srv/api\"\nprimary = true")
+            + "\n[[workspaces.dirs]]\npath = \"/srv/api\"\nlabel = \"api\"\nprimary = true\n";
+
+        let errors = validate(&strip_comments(&text), "x", &[], None).unwrap_err();
+        assert_eq!(errors.len(), 4);
+
+        let commented = with_errors(&text, &errors);
+        assert!(commented.contains("# The workspace cannot be renamed here"));
+        assert_eq!(strip_comments(&commented), strip_comments(&text));
+    }
+}
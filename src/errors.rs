@@ -1,5 +1,38 @@
+use rusqlite::ffi;
+
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Database Erorr")]
     DbError(String),
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+    /// Workspaces, directories, tasks and the like which do not exist
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0}")]
+    InvalidPath(String),
+    #[error("{0}")]
+    InvalidFile(String),
+    /// Commands run by ws which failed
+    #[error("{0}")]
+    CommandFailed(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Unique constraint violations (duplicate names, duplicate directories)
+/// become [`Error::AlreadyExists`], everything else a [`Error::DbError`]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::SqliteFailure(e, msg)
+                if e.extended_code == ffi::SQLITE_CONSTRAINT_UNIQUE
+                    || e.extended_code == ffi::SQLITE_CONSTRAINT_PRIMARYKEY =>
+            {
+                Error::AlreadyExists(msg.unwrap_or_else(|| e.to_string()))
+            }
+            err => Error::DbError(err.to_string()),
+        }
+    }
 }
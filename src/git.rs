@@ -0,0 +1,432 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+
+/// What identifies a repository independent of where it is checked out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitIdentity {
+    /// First commit of the history
+    pub root_commit: Option<String>,
+    /// Url of the `origin` remote, or the first remote
+    pub remote: Option<String>,
+    /// Branch checked out by a fresh clone, the `HEAD` of the remote
+    /// or else the branch checked out when recorded
+    pub default_branch: Option<String>,
+}
+
+impl GitIdentity {
+    /// Checks if both identities point to the same repository,
+    /// the root commit decides when both have one
+    pub fn matches(&self, other: &GitIdentity) -> bool {
+        match (&self.root_commit, &other.root_commit) {
+            (Some(a), Some(b)) => a == b,
+            _ => match (&self.remote, &other.remote) {
+                (Some(a), Some(b)) => normalize_remote(a) == normalize_remote(b),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Runs git with `args` inside `dir`, returns the trimmed output on success
+pub fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Runs git like [`git`], on failure the error output is returned
+pub fn git_checked(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| format!("Cannot run git: {}", err))?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+/// Checks if `dir` is the top level of a git repository or worktree
+pub fn is_repo(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+/// Identity of the repository in `dir`, `None` when it is not one
+pub fn identity(dir: &Path) -> Option<GitIdentity> {
+    if !is_repo(dir) {
+        return None;
+    }
+
+    // a history can have several roots (merged unrelated histories), the
+    // oldest one is listed last
+    let root_commit = git(dir, &["rev-list", "--max-parents=0", "HEAD"])
+        .and_then(|roots| roots.lines().last().map(String::from));
+
+    let remote_name = git(dir, &["remote"]).and_then(|remotes| {
+        let mut remotes = remotes.lines();
+        match remotes.clone().any(|name| name == "origin") {
+            true => Some(String::from("origin")),
+            false => remotes.next().map(String::from),
+        }
+    });
+    let remote = remote_name
+        .as_ref()
+        .and_then(|name| git(dir, &["remote", "get-url", name]));
+
+    let remote_head = remote_name.and_then(|name| {
+        let head = git(
+            dir,
+            &[
+                "symbolic-ref",
+                "--short",
+                &format!("refs/remotes/{}/HEAD", name),
+            ],
+        )?;
+        head.strip_prefix(&format!("{}/", name)).map(String::from)
+    });
+    let default_branch =
+        remote_head.or_else(|| git(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"]));
+
+    Some(GitIdentity {
+        root_commit,
+        remote,
+        default_branch,
+    })
+}
+
+/// Working state of a repository as shown by `ws status`
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RepoStatus {
+    /// Checked out branch, `None` on a detached head
+    pub branch: Option<String>,
+    /// Commits ahead and behind the upstream, `None` without an upstream
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+    /// Changed tracked files, staged or not, including conflicts
+    pub changed: u32,
+    pub untracked: u32,
+    pub stashes: u32,
+    /// `rebase`, `merge`, `cherry-pick` or `revert` in progress
+    pub operation: Option<&'static str>,
+}
+
+impl RepoStatus {
+    pub fn is_dirty(&self) -> bool {
+        self.changed > 0 || self.untracked > 0
+    }
+}
+
+/// Status of the repository in `dir`, `None` when it is not one
+pub fn status(dir: &Path) -> Option<RepoStatus> {
+    if !is_repo(dir) {
+        return None;
+    }
+
+    let porcelain = git(dir, &["status", "--porcelain=v2", "--branch"])?;
+    let mut status = parse_status(&porcelain);
+
+    status.stashes = git(dir, &["stash", "list"])
+        .map(|list| list.lines().count() as u32)
+        .unwrap_or(0);
+
+    if let Some(git_dir) = git(dir, &["rev-parse", "--absolute-git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        status.operation = [
+            ("rebase-merge", "rebase"),
+            ("rebase-apply", "rebase"),
+            ("MERGE_HEAD", "merge"),
+            ("CHERRY_PICK_HEAD", "cherry-pick"),
+            ("REVERT_HEAD", "revert"),
+        ]
+        .iter()
+        .find(|(file, _)| git_dir.join(file).exists())
+        .map(|(_, operation)| *operation);
+    }
+
+    Some(status)
+}
+
+/// Reads the output of `git status --porcelain=v2 --branch`
+fn parse_status(porcelain: &str) -> RepoStatus {
+    let mut status = RepoStatus::default();
+
+    for line in porcelain.lines() {
+        match line.split_once(' ') {
+            Some(("#", header)) => match header.split_once(' ') {
+                Some(("branch.head", head)) if head != "(detached)" => {
+                    status.branch = Some(String::from(head))
+                }
+                Some(("branch.ab", counts)) => {
+                    let mut counts = counts
+                        .split(' ')
+                        .map(|count| count.trim_start_matches(['+', '-']).parse().ok());
+                    status.ahead = counts.next().flatten();
+                    status.behind = counts.next().flatten();
+                }
+                _ => {}
+            },
+            Some(("1" | "2" | "u", _)) => status.changed += 1,
+            Some(("?", _)) => status.untracked += 1,
+            _ => {}
+        }
+    }
+
+    status
+}
+
+/// Checks if git arguments `args` create a branch and switch to it,
+/// like `checkout -b name` or `switch -c name`
+pub fn creates_branch(args: &[String]) -> bool {
+    let flags: &[&str] = match args.first().map(String::as_str) {
+        Some("checkout") => &["-b", "-B"],
+        Some("switch") => &["-c", "-C", "--create", "--force-create"],
+        _ => return false,
+    };
+
+    args[1..].iter().any(|arg| {
+        flags.contains(&arg.as_str())
+            || flags
+                .iter()
+                .any(|f| f.starts_with("--") && arg.starts_with(&format!("{}=", f)))
+    })
+}
+
+/// Checks if `name` is allowed as a branch name
+pub fn is_valid_branch(name: &str) -> bool {
+    Command::new("git")
+        .args(["check-ref-format", "--branch", name])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Adds a worktree of repository `repo` at `path` with `branch` checked out.
+/// The branch is created from `HEAD` unless it exists locally or on a remote
+pub fn add_worktree(repo: &Path, path: &Path, branch: &str) -> Result<(), String> {
+    let local = git(
+        repo,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", branch),
+        ],
+    );
+    let remote = git(repo, &["branch", "-r", "--list", &format!("*/{}", branch)])
+        .is_some_and(|branches| !branches.is_empty());
+    let path = path.to_string_lossy();
+
+    let args = match local.is_some() || remote {
+        true => vec!["worktree", "add", "--quiet", &path, branch],
+        false => vec!["worktree", "add", "--quiet", "-b", branch, &path],
+    };
+    git_checked(repo, &args).map(|_| ())
+}
+
+/// Main working tree of the repository `dir` is a linked worktree of,
+/// `None` when `dir` is not a linked worktree
+pub fn main_worktree(dir: &Path) -> Option<PathBuf> {
+    // the `.git` of a linked worktree is a file pointing to the repository
+    if !dir.join(".git").is_file() {
+        return None;
+    }
+
+    let common = git(
+        dir,
+        &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+    )?;
+    Path::new(&common).parent().map(Path::to_path_buf)
+}
+
+/// Removes the linked worktree at `path`, with `force` even when it has changes
+pub fn remove_worktree(path: &Path, force: bool) -> Result<(), String> {
+    let repo =
+        main_worktree(path).ok_or_else(|| format!("{} is not a worktree", path.display()))?;
+    let path = path.to_string_lossy();
+
+    let mut args = vec!["worktree", "remove"];
+    if force {
+        args.push("--force");
+    }
+    args.push(&path);
+
+    git_checked(&repo, &args).map(|_| ())
+}
+
+/// Checks if `branch` is contained in the checked out `HEAD` of `repo`
+pub fn is_merged(repo: &Path, branch: &str) -> bool {
+    git_checked(repo, &["merge-base", "--is-ancestor", branch, "HEAD"]).is_ok()
+}
+
+/// Reduces a remote url to `host/owner/repo` so ssh and https urls compare equal
+pub fn normalize_remote(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let without_scheme = match url.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        // scp like syntax `git@host:owner/repo`
+        None => url.replacen(':', "/", 1),
+    };
+
+    let without_user = match without_scheme.split_once('@') {
+        Some((user, rest)) if !user.contains('/') => rest.to_string(),
+        _ => without_scheme,
+    };
+
+    without_user.to_lowercase()
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use std::path::Path;
+    use std::process::Command;
+
+    /// Runs git for a test, panicking on failure
+    pub fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=ws", "-c", "user.email=ws@localhost"])
+            .args([
+                "-c",
+                "init.defaultBranch=main",
+                "-c",
+                "commit.gpgsign=false",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("Cannot run git");
+        assert!(
+            status.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&status.stderr)
+        );
+    }
+
+    /// Creates a repository with one commit in `dir`
+    pub fn init_repo(dir: &Path) {
+        std::fs::create_dir_all(dir).unwrap();
+        run_git(dir, &["init", "-q"]);
+        std::fs::write(dir.join("README"), dir.to_string_lossy().as_bytes()).unwrap();
+        run_git(dir, &["add", "README"]);
+        run_git(dir, &["commit", "-q", "-m", "initial"]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{init_repo, run_git};
+    use super::*;
+
+    #[test]
+    fn normalizes_ssh_and_https_remotes() {
+        let expected = "github.com/owner/repo";
+
+        assert_eq!(normalize_remote("git@github.com:owner/repo.git"), expected);
+        assert_eq!(normalize_remote("https://github.com/owner/repo"), expected);
+        assert_eq!(
+            normalize_remote("ssh://git@github.com/Owner/repo.git/"),
+            expected
+        );
+    }
+
+    #[test]
+    fn identifies_clones_of_the_same_repository() {
+        let root = tempfile::tempdir().unwrap();
+        let original = root.path().join("original");
+        let other = root.path().join("other");
+        init_repo(&original);
+        init_repo(&other);
+        run_git(root.path(), &["clone", "-q", "original", "clone"]);
+
+        let original = identity(&original).unwrap();
+        let clone = identity(&root.path().join("clone")).unwrap();
+        let other = identity(&other).unwrap();
+
+        assert!(clone.remote.is_some());
+        assert_eq!(clone.default_branch.as_deref(), Some("main"));
+        assert!(original.matches(&clone));
+        assert!(!original.matches(&other));
+        assert!(identity(root.path()).is_none());
+    }
+
+    #[test]
+    fn adds_and_removes_worktrees() {
+        let root = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(root.path()).unwrap();
+        let repo = root.join("api");
+        init_repo(&repo);
+        run_git(&repo, &["branch", "existing"]);
+
+        let created = root.join("wt/api-new");
+        add_worktree(&repo, &created, "feature/new").unwrap();
+        add_worktree(&repo, &root.join("wt/api-existing"), "existing").unwrap();
+
+        assert_eq!(
+            status(&created).unwrap().branch.as_deref(),
+            Some("feature/new")
+        );
+        assert_eq!(main_worktree(&created), Some(repo.clone()));
+        assert_eq!(main_worktree(&repo), None);
+        assert!(is_merged(&repo, "feature/new"));
+
+        std::fs::write(created.join("README"), "changed").unwrap();
+        run_git(&created, &["commit", "-q", "-am", "change"]);
+        assert!(!is_merged(&repo, "feature/new"));
+
+        remove_worktree(&created, false).unwrap();
+        assert!(!created.exists());
+        assert!(add_worktree(&repo, &root.join("wt/again"), "existing").is_err());
+    }
+
+    #[test]
+    fn detects_branch_creation() {
+        let args = |args: &str| args.split(' ').map(String::from).collect::<Vec<_>>();
+
+        assert!(creates_branch(&args("checkout -b feature/foo")));
+        assert!(creates_branch(&args("switch --create=feature/foo")));
+        assert!(!creates_branch(&args("switch main")));
+        assert!(!creates_branch(&args("fetch -b")));
+    }
+
+    #[test]
+    fn reads_the_status_of_a_repository() {
+        let root = tempfile::tempdir().unwrap();
+        let origin = root.path().join("origin");
+        init_repo(&origin);
+        run_git(root.path(), &["clone", "-q", "origin", "clone"]);
+
+        let clone = root.path().join("clone");
+        std::fs::write(clone.join("README"), "changed").unwrap();
+        run_git(&clone, &["commit", "-q", "-am", "change"]);
+        std::fs::write(clone.join("new"), "").unwrap();
+        std::fs::write(clone.join("README"), "changed again").unwrap();
+        run_git(&clone, &["stash", "-q"]);
+        std::fs::write(clone.join("README"), "dirty").unwrap();
+
+        let status = status(&clone).unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!((status.ahead, status.behind), (Some(1), Some(0)));
+        assert_eq!(
+            (status.changed, status.untracked, status.stashes),
+            (1, 1, 1)
+        );
+        assert_eq!(status.operation, None);
+
+        let origin = super::status(&origin).unwrap();
+        assert_eq!((origin.ahead, origin.is_dirty()), (None, false));
+        assert!(super::status(root.path()).is_none());
+    }
+}
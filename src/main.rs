@@ -1,13 +1,36 @@
 #[macro_use]
 extern crate prettytable;
 
+use document::{Format, Strategy};
+use sources::Source;
 use std::path::PathBuf;
 use structopt::StructOpt;
+use utils::PathOptions;
 
+mod browse;
+mod clone;
 mod command_handlers;
+mod commits;
 mod db;
+mod doctor;
+mod document;
+mod edit;
 mod errors;
+mod git;
+mod manifest;
+mod multi;
+mod project;
+mod roots;
+mod scan;
+mod services;
+mod settings;
+mod snapshot;
+mod sources;
+mod status;
+mod tasks;
 mod utils;
+mod vscode;
+mod watch;
 mod workspace;
 
 #[derive(StructOpt, Debug)]
@@ -29,6 +52,21 @@ enum Operation {
     Open {
         #[structopt(short = "w", long = "workspace")]
         workspace: String,
+
+        #[structopt(short = "d", long = "dir", help = "only open this directory")]
+        dir: Option<String>,
+    },
+    #[structopt(about = "Open a shell in a directory of a workspace")]
+    Shell {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(
+            short = "d",
+            long = "dir",
+            help = "label or path of the directory, defaults to the primary one"
+        )]
+        dir: Option<String>,
     },
     #[structopt(about = "Add new workspace")]
     Add {
@@ -37,6 +75,9 @@ enum Operation {
 
         #[structopt(short = "n", long = "name")]
         name: Option<String>,
+
+        #[structopt(flatten)]
+        path_options: PathOptions,
     },
     #[structopt(about = "deletes a workspace")]
     Del {
@@ -47,9 +88,477 @@ enum Operation {
     Editor {
         #[structopt(short = "n", long = "name")]
         name: String,
+
+        #[structopt(
+            short = "w",
+            long = "workspace",
+            help = "only change the editor of this workspace"
+        )]
+        workspace: Option<String>,
+    },
+    #[structopt(about = "edit a workspace as TOML in $EDITOR")]
+    Edit {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+    },
+    #[structopt(about = "rename a workspace")]
+    Rename { old: String, new: String },
+    #[structopt(about = "copy a workspace with all its directories")]
+    Copy { source: String, destination: String },
+    #[structopt(about = "merge workspaces into one, removing duplicate directories")]
+    Merge {
+        #[structopt(required = true, min_values = 2)]
+        workspaces: Vec<String>,
+
+        #[structopt(long = "into")]
+        into: String,
+    },
+    #[structopt(about = "move directories of a workspace into another workspace")]
+    Split {
+        workspace: String,
+
+        #[structopt(long = "dirs", required = true, min_values = 1)]
+        dirs: Vec<String>,
+
+        #[structopt(long = "into")]
+        into: String,
     },
     #[structopt(about = "list all workspaces")]
     List,
+    #[structopt(about = "check workspaces for missing directories and other problems")]
+    Doctor {
+        #[structopt(long = "fix", help = "update repositories found again below the roots")]
+        fix: bool,
+    },
+    #[structopt(about = "move all directories below a path to another path")]
+    Relocate {
+        #[structopt(parse(from_os_str))]
+        old_prefix: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        new_prefix: PathBuf,
+
+        #[structopt(short = "w", long = "workspace", help = "only relocate this workspace")]
+        workspace: Option<String>,
+
+        #[structopt(long = "dry-run", help = "only show what would change")]
+        dry_run: bool,
+    },
+    #[structopt(about = "list or change the roots directories are stored relative to")]
+    Roots {
+        #[structopt(subcommand)]
+        root_operation: Option<RootOperation>,
+    },
+    #[structopt(about = "import workspaces from an exported document or another tool")]
+    Import {
+        #[structopt(parse(from_os_str), help = "json or toml document written by export")]
+        file: Option<PathBuf>,
+
+        #[structopt(long = "format", help = "json or toml, defaults to the file extension")]
+        format: Option<Format>,
+
+        #[structopt(
+            long = "strategy",
+            default_value = "skip",
+            help = "for existing workspaces: skip, overwrite, merge or rename"
+        )]
+        strategy: Strategy,
+
+        #[structopt(long = "dry-run", help = "only show what would change")]
+        dry_run: bool,
+
+        #[structopt(subcommand)]
+        source: Option<ImportSource>,
+    },
+    #[structopt(about = "export workspaces as a document or for another tool")]
+    Export {
+        #[structopt(long = "format", default_value = "json", help = "json or toml")]
+        format: Format,
+
+        #[structopt(short = "w", long = "workspace", help = "defaults to all workspaces")]
+        workspaces: Vec<String>,
+
+        #[structopt(
+            short = "o",
+            long = "output",
+            parse(from_os_str),
+            help = "file to write, prints to stdout when missing"
+        )]
+        output: Option<PathBuf>,
+
+        #[structopt(subcommand)]
+        target: Option<ExportTarget>,
+    },
+    #[structopt(about = "show how the workspaces differ from a manifest")]
+    Plan {
+        #[structopt(short = "f", long = "file", parse(from_os_str))]
+        file: PathBuf,
+
+        #[structopt(
+            long = "prune",
+            help = "also delete workspaces the manifest does not declare"
+        )]
+        prune: bool,
+    },
+    #[structopt(about = "make the workspaces match a manifest")]
+    Apply {
+        #[structopt(short = "f", long = "file", parse(from_os_str))]
+        file: PathBuf,
+
+        #[structopt(
+            long = "prune",
+            help = "also delete workspaces the manifest does not declare"
+        )]
+        prune: bool,
+
+        #[structopt(short = "y", long = "yes", help = "do not ask before deleting")]
+        yes: bool,
+    },
+    #[structopt(about = "show the git status of every directory of a workspace")]
+    Status {
+        #[structopt(
+            short = "w",
+            long = "workspace",
+            help = "defaults to the workspaces containing the current directory"
+        )]
+        workspace: Option<String>,
+
+        #[structopt(long = "all", conflicts_with = "workspace", help = "all workspaces")]
+        all: bool,
+
+        #[structopt(long = "format", default_value = "table", help = "table or json")]
+        format: status::Format,
+    },
+    #[structopt(
+        about = "run git in every repository of a workspace, e.g. `ws git -w team pull --ff-only`",
+        setting = structopt::clap::AppSettings::TrailingVarArg
+    )]
+    Git {
+        #[structopt(
+            short = "w",
+            long = "workspace",
+            help = "defaults to the workspaces containing the current directory"
+        )]
+        workspace: Option<String>,
+
+        #[structopt(long = "all", conflicts_with = "workspace", help = "all workspaces")]
+        all: bool,
+
+        #[structopt(short = "j", long = "jobs", help = "how many repositories at once")]
+        jobs: Option<usize>,
+
+        #[structopt(
+            long = "continue-on-error",
+            help = "keep going in the other repositories after a failure"
+        )]
+        continue_on_error: bool,
+
+        #[structopt(
+            required = true,
+            allow_hyphen_values = true,
+            help = "arguments for git"
+        )]
+        args: Vec<String>,
+    },
+    #[structopt(
+        about = "run a command in every directory of a workspace, e.g. `ws exec -w team -- cargo test`",
+        setting = structopt::clap::AppSettings::TrailingVarArg
+    )]
+    Exec {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(
+            long = "dirs",
+            require_delimiter = true,
+            help = "only these directories, by name, label or path, e.g. `api,web`"
+        )]
+        dirs: Vec<String>,
+
+        #[structopt(
+            short = "j",
+            long = "parallel",
+            help = "how many directories at once, defaults to one after another"
+        )]
+        parallel: Option<usize>,
+
+        #[structopt(
+            long = "fail-fast",
+            help = "start no further directories after a failure"
+        )]
+        fail_fast: bool,
+
+        #[structopt(
+            long = "group",
+            help = "show the output of each directory at once when it finishes"
+        )]
+        group: bool,
+
+        #[structopt(
+            required = true,
+            allow_hyphen_values = true,
+            help = "program and arguments, or a single shell script"
+        )]
+        command: Vec<String>,
+    },
+    #[structopt(
+        about = "run a command again whenever files of a workspace change, e.g. `ws watch -w team -- cargo check`",
+        setting = structopt::clap::AppSettings::TrailingVarArg
+    )]
+    Watch {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(
+            long = "all",
+            help = "run in every directory, not only in the ones which changed"
+        )]
+        all: bool,
+
+        #[structopt(
+            long = "debounce",
+            default_value = "300",
+            help = "milliseconds without further changes to wait before running"
+        )]
+        debounce: u64,
+
+        #[structopt(
+            required = true,
+            allow_hyphen_values = true,
+            help = "program and arguments, or a single shell script"
+        )]
+        command: Vec<String>,
+    },
+    #[structopt(about = "run a task of a workspace, the tasks it depends on first")]
+    Run {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        task: String,
+
+        #[structopt(
+            long = "dry-run",
+            help = "only list the steps in the order they would run"
+        )]
+        dry_run: bool,
+    },
+    #[structopt(about = "list the tasks of a workspace, or change them")]
+    Tasks {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: Option<String>,
+
+        #[structopt(subcommand)]
+        task_operation: Option<TaskOperation>,
+    },
+    #[structopt(about = "start the services of a workspace in the background")]
+    Up {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(help = "only these services, defaults to all")]
+        services: Vec<String>,
+    },
+    #[structopt(about = "stop the services of a workspace")]
+    Down {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(help = "only these services, defaults to all")]
+        services: Vec<String>,
+
+        #[structopt(
+            long = "timeout",
+            default_value = "10",
+            help = "seconds to wait after SIGTERM before sending SIGKILL"
+        )]
+        timeout: u64,
+    },
+    #[structopt(about = "show the services of a workspace, or the running ones of all workspaces")]
+    Ps {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: Option<String>,
+    },
+    #[structopt(about = "show the output of a service")]
+    Logs {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        service: String,
+
+        #[structopt(short = "f", long = "follow", help = "keep printing new output")]
+        follow: bool,
+
+        #[structopt(short = "n", long = "lines", default_value = "50")]
+        lines: usize,
+    },
+    #[structopt(about = "list the services of a workspace, or change them")]
+    Services {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: Option<String>,
+
+        #[structopt(subcommand)]
+        service_operation: Option<ServiceOperation>,
+    },
+    #[structopt(
+        about = "run a service and restart it on failure, used by `ws up`",
+        setting = structopt::clap::AppSettings::Hidden
+    )]
+    Supervise {
+        #[structopt(long = "log", parse(from_os_str))]
+        log: PathBuf,
+
+        #[structopt(long = "restart")]
+        restart: bool,
+
+        script: String,
+    },
+    #[structopt(about = "create a worktree for a branch in every repository of a workspace")]
+    Branch {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(help = "branch to check out, created when missing")]
+        branch: String,
+
+        #[structopt(
+            long = "location",
+            parse(from_os_str),
+            help = "directory to create the worktrees in, see `ws config`"
+        )]
+        location: Option<PathBuf>,
+
+        #[structopt(
+            long = "remove",
+            conflicts_with = "location",
+            help = "remove the worktrees and the workspace of the branch"
+        )]
+        remove: bool,
+
+        #[structopt(
+            long = "force",
+            requires = "remove",
+            help = "remove even with changes or an unmerged branch"
+        )]
+        force: bool,
+    },
+    #[structopt(about = "open the web pages of the repositories of a workspace")]
+    Browse {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(long = "dir", help = "only this directory, by label or path")]
+        dir: Option<String>,
+
+        #[structopt(long = "pr", help = "the pull or merge requests")]
+        pull_requests: bool,
+
+        #[structopt(long = "issues", conflicts_with = "pull-requests")]
+        issues: bool,
+
+        #[structopt(
+            long = "branch",
+            conflicts_with_all = &["pull-requests", "issues"],
+            help = "the checked out branch"
+        )]
+        branch: bool,
+
+        #[structopt(long = "print", help = "print the urls instead of opening them")]
+        print: bool,
+    },
+    #[structopt(about = "commits of all repositories of a workspace, newest first")]
+    Log {
+        #[structopt(
+            short = "w",
+            long = "workspace",
+            help = "defaults to the workspaces containing the current directory"
+        )]
+        workspace: Option<String>,
+
+        #[structopt(long = "all", conflicts_with = "workspace", help = "all workspaces")]
+        all: bool,
+
+        #[structopt(
+            long = "since",
+            default_value = "yesterday",
+            help = "like `yesterday`, `monday` or `2026-01-31`"
+        )]
+        since: String,
+
+        #[structopt(long = "until")]
+        until: Option<String>,
+
+        #[structopt(long = "author", help = "`me` for your own commits")]
+        author: Option<String>,
+
+        #[structopt(long = "format", default_value = "text", help = "text or markdown")]
+        format: commits::Format,
+
+        #[structopt(long = "summary", help = "group the commits by day and directory")]
+        summary: bool,
+    },
+    #[structopt(about = "record the checked out commits of a workspace, or restore them")]
+    Snapshot {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: Option<String>,
+
+        #[structopt(help = "name of the new snapshot, defaults to the current time")]
+        name: Option<String>,
+
+        #[structopt(subcommand)]
+        snapshot_operation: Option<SnapshotOperation>,
+    },
+    #[structopt(about = "clone the missing repositories of a workspace from their remotes")]
+    Clone {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(
+            long = "root",
+            parse(from_os_str),
+            help = "directory to recreate the workspace in, keeping the relative locations"
+        )]
+        root: Option<PathBuf>,
+
+        #[structopt(long = "lazy", help = "clone each repository on the next `ws open`")]
+        lazy: bool,
+
+        #[structopt(short = "j", long = "jobs", help = "how many repositories at once")]
+        jobs: Option<usize>,
+    },
+    #[structopt(about = "list or change settings")]
+    Config {
+        #[structopt(subcommand)]
+        config_operation: Option<ConfigOperation>,
+    },
+    #[structopt(about = "find the projects below a directory and add them as workspaces")]
+    Scan {
+        #[structopt(parse(from_os_str))]
+        root: PathBuf,
+
+        #[structopt(long = "depth", help = "how many levels below the root to search")]
+        depth: Option<usize>,
+
+        #[structopt(
+            long = "marker",
+            help = "file or directory marking a project, e.g. Cargo.toml, defaults to .git"
+        )]
+        markers: Vec<String>,
+
+        #[structopt(
+            short = "w",
+            long = "workspace",
+            help = "add all projects to this workspace instead of one workspace each"
+        )]
+        workspace: Option<String>,
+
+        #[structopt(short = "y", long = "yes", help = "add all projects without asking")]
+        yes: bool,
+    },
+    #[structopt(about = "apply the .ws.toml project files which changed")]
+    SyncProjects {
+        #[structopt(short = "w", long = "workspace", help = "defaults to all workspaces")]
+        workspace: Option<String>,
+    },
     #[structopt(about = "Dir operations")]
     Dir {
         #[structopt(short = "w", long = "workspace")]
@@ -66,21 +575,251 @@ enum DirOperation {
     Add {
         #[structopt(short = "p", long = "path", parse(from_os_str))]
         path: PathBuf,
+
+        #[structopt(short = "l", long = "label")]
+        label: Option<String>,
+
+        #[structopt(flatten)]
+        path_options: PathOptions,
     },
     #[structopt(about = "Delete a directory from a workspace")]
-    Del,
+    Del {
+        #[structopt(help = "label or path of the directory, asks when missing")]
+        dir: Option<String>,
+    },
+    #[structopt(about = "Set or remove the label of a directory")]
+    Label {
+        #[structopt(help = "label or path of the directory")]
+        dir: String,
+
+        #[structopt(help = "new label, removes the label when missing")]
+        label: Option<String>,
+    },
+    #[structopt(about = "Change the order of the directories")]
+    Reorder {
+        #[structopt(
+            required = true,
+            help = "directories in their new order, unlisted ones follow"
+        )]
+        dirs: Vec<String>,
+    },
+    #[structopt(about = "Make a directory the primary one")]
+    Primary {
+        #[structopt(help = "label or path of the directory")]
+        dir: String,
+    },
     #[structopt(about = "Set Init Script")]
     Init,
 }
 
+#[derive(StructOpt, Debug)]
+enum ImportSource {
+    #[structopt(about = "Import the folders of a VS Code `.code-workspace` file")]
+    Vscode {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+
+        #[structopt(short = "n", long = "name", help = "defaults to the file name")]
+        name: Option<String>,
+    },
+    #[structopt(about = "Pick workspaces from the folders recently opened in VS Code")]
+    VscodeRecent {
+        #[structopt(
+            long = "file",
+            parse(from_os_str),
+            help = "state.vscdb or storage.json, found automatically when missing"
+        )]
+        file: Option<PathBuf>,
+    },
+    #[structopt(about = "Pick workspaces from the bookmarks of Emacs projectile")]
+    Projectile {
+        #[structopt(
+            long = "file",
+            parse(from_os_str),
+            help = "defaults to ~/.emacs.d/projectile-bookmarks.eld"
+        )]
+        file: Option<PathBuf>,
+    },
+    #[structopt(about = "Pick workspaces from the history of project.nvim")]
+    Nvim {
+        #[structopt(
+            long = "file",
+            parse(from_os_str),
+            help = "defaults to the project_history in the neovim data directory"
+        )]
+        file: Option<PathBuf>,
+    },
+    #[structopt(about = "Pick workspaces from the repositories cloned with ghq")]
+    Ghq {
+        #[structopt(
+            long = "file",
+            parse(from_os_str),
+            help = "saved output of `ghq list --full-path`, runs it when missing"
+        )]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+enum ExportTarget {
+    #[structopt(about = "Export as a VS Code `.code-workspace` file")]
+    Vscode {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(
+            short = "o",
+            long = "output",
+            parse(from_os_str),
+            help = "file to write, prints to stdout when missing"
+        )]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+enum RootOperation {
+    #[structopt(about = "Add a root or change its path, e.g. `ws roots add code ~/code`")]
+    Add {
+        name: String,
+
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    #[structopt(about = "Remove a root")]
+    Remove { name: String },
+}
+
+#[derive(StructOpt, Debug)]
+enum TaskOperation {
+    #[structopt(
+        about = "Add a task, e.g. `ws tasks add -w team test --depends build -- cargo test`",
+        setting = structopt::clap::AppSettings::TrailingVarArg
+    )]
+    Add {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        name: String,
+
+        #[structopt(
+            long = "dir",
+            help = "only in this directory, defaults to every directory"
+        )]
+        dir: Option<String>,
+
+        #[structopt(
+            long = "depends",
+            require_delimiter = true,
+            help = "tasks run first, `task` in the same directory or `dir:task`"
+        )]
+        depends: Vec<String>,
+
+        #[structopt(required = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    #[structopt(about = "Remove a task added with `ws tasks add`")]
+    Remove {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        name: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum ServiceOperation {
+    #[structopt(
+        about = "Add a service, e.g. `ws services add -w team web --dir web -- npm run dev`",
+        setting = structopt::clap::AppSettings::TrailingVarArg
+    )]
+    Add {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        name: String,
+
+        #[structopt(
+            long = "dir",
+            help = "directory to run in, defaults to the primary one"
+        )]
+        dir: Option<String>,
+
+        #[structopt(long = "restart", help = "start it again when it fails")]
+        restart: bool,
+
+        #[structopt(required = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    #[structopt(about = "Add the `name: command` lines of a Procfile as services")]
+    Import {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        #[structopt(
+            long = "dir",
+            help = "directory to run in, defaults to the primary one"
+        )]
+        dir: Option<String>,
+
+        #[structopt(parse(from_os_str), help = "defaults to the Procfile of the directory")]
+        file: Option<PathBuf>,
+
+        #[structopt(long = "restart", help = "start them again when they fail")]
+        restart: bool,
+    },
+    #[structopt(about = "Remove a service")]
+    Remove {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        name: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum SnapshotOperation {
+    #[structopt(about = "Check out the recorded commits, refused with uncommitted changes")]
+    Restore {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        name: String,
+    },
+    #[structopt(about = "List the snapshots")]
+    List {
+        #[structopt(short = "w", long = "workspace", help = "defaults to all workspaces")]
+        workspace: Option<String>,
+    },
+    #[structopt(about = "Delete a snapshot")]
+    Delete {
+        #[structopt(short = "w", long = "workspace")]
+        workspace: String,
+
+        name: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+enum ConfigOperation {
+    #[structopt(about = "Change a setting, e.g. `ws config set worktrees ~/worktrees`")]
+    Set { name: String, value: String },
+    #[structopt(about = "Reset a setting to its default")]
+    Unset { name: String },
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     db::initialize_db()?;
 
     let options: Options = Options::from_args();
 
     match options.command {
-        Operation::Add { name, path } => {
-            command_handlers::add_workspace(name, path)?;
+        Operation::Add {
+            name,
+            path,
+            path_options,
+        } => {
+            command_handlers::add_workspace(name, path, path_options)?;
             println!("Workspace added")
         }
         Operation::Del { name } => {
@@ -91,22 +830,311 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Operation::List => {
             command_handlers::print_workspaces()?;
         }
-        Operation::Open { workspace } => {
-            command_handlers::open_workspace(workspace).expect("Error opening workspace")
+        Operation::Open { workspace, dir } => {
+            command_handlers::open_workspace(workspace, dir).expect("Error opening workspace")
+        }
+        Operation::Shell { workspace, dir } => {
+            command_handlers::open_shell(workspace, dir)?;
         }
-        Operation::Editor { name } => {
+        Operation::Editor {
+            name,
+            workspace: None,
+        } => {
             command_handlers::update_editor(name.clone()).expect("Error updating editor");
             println!("Editor updated to {}", name);
         }
+        Operation::Editor {
+            name,
+            workspace: Some(workspace),
+        } => {
+            command_handlers::update_workspace_editor(workspace.clone(), name.clone())?;
+            println!("Editor of {} updated to {}", workspace, name);
+        }
+        Operation::Edit { workspace } => command_handlers::edit_workspace(workspace)?,
+        Operation::Rename { old, new } => {
+            command_handlers::rename_workspace(old, new)?;
+        }
+        Operation::Copy {
+            source,
+            destination,
+        } => {
+            command_handlers::copy_workspace(source, destination)?;
+        }
+        Operation::Merge { workspaces, into } => {
+            command_handlers::merge_workspaces(workspaces, into)?;
+        }
+        Operation::Split {
+            workspace,
+            dirs,
+            into,
+        } => {
+            command_handlers::split_workspace(workspace, dirs, into)?;
+        }
+        Operation::Doctor { fix } => {
+            command_handlers::doctor(fix)?;
+        }
+        Operation::Relocate {
+            old_prefix,
+            new_prefix,
+            workspace,
+            dry_run,
+        } => {
+            command_handlers::relocate_dirs(old_prefix, new_prefix, workspace, dry_run)?;
+        }
+        Operation::Roots { root_operation } => match root_operation {
+            None => command_handlers::print_roots()?,
+            Some(RootOperation::Add { name, path }) => command_handlers::add_root(name, path)?,
+            Some(RootOperation::Remove { name }) => command_handlers::remove_root(name)?,
+        },
+        Operation::Import {
+            file,
+            format,
+            strategy,
+            dry_run,
+            source,
+        } => match (source, file) {
+            (Some(ImportSource::Vscode { file, name }), _) => {
+                command_handlers::import_vscode(file, name)?
+            }
+            (Some(ImportSource::VscodeRecent { file }), _) => {
+                command_handlers::import_projects(Source::VscodeRecent, file)?
+            }
+            (Some(ImportSource::Projectile { file }), _) => {
+                command_handlers::import_projects(Source::Projectile, file)?
+            }
+            (Some(ImportSource::Nvim { file }), _) => {
+                command_handlers::import_projects(Source::Nvim, file)?
+            }
+            (Some(ImportSource::Ghq { file }), _) => {
+                command_handlers::import_projects(Source::Ghq, file)?
+            }
+            (None, Some(file)) => {
+                command_handlers::import_document(file, format, strategy, dry_run)?
+            }
+            (None, None) => {
+                eprintln!("Give a file to import, see `ws import --help`");
+                return Err(errors::Error::InvalidFile(String::from("No file")).into());
+            }
+        },
+        Operation::Export {
+            format,
+            workspaces,
+            output,
+            target,
+        } => match target {
+            Some(ExportTarget::Vscode { workspace, output }) => {
+                command_handlers::export_vscode(workspace, output)?
+            }
+            None => command_handlers::export_document(workspaces, format, output)?,
+        },
+        Operation::Plan { file, prune } => command_handlers::plan_manifest(file, prune)?,
+        Operation::Apply { file, prune, yes } => {
+            command_handlers::apply_manifest(file, prune, yes)?
+        }
+        Operation::Status {
+            workspace,
+            all,
+            format,
+        } => command_handlers::print_status(workspace, all, format)?,
+        Operation::Git {
+            workspace,
+            all,
+            jobs,
+            continue_on_error,
+            args,
+        } => command_handlers::git_command(workspace, all, jobs, continue_on_error, args)?,
+        Operation::Exec {
+            workspace,
+            dirs,
+            parallel,
+            fail_fast,
+            group,
+            command,
+        } => command_handlers::exec_command(workspace, dirs, parallel, fail_fast, group, command)?,
+        Operation::Watch {
+            workspace,
+            all,
+            debounce,
+            command,
+        } => command_handlers::watch(workspace, all, debounce, command)?,
+        Operation::Run {
+            workspace,
+            task,
+            dry_run,
+        } => command_handlers::run_task(workspace, task, dry_run)?,
+        Operation::Tasks {
+            workspace,
+            task_operation,
+        } => match (task_operation, workspace) {
+            (
+                Some(TaskOperation::Add {
+                    workspace,
+                    name,
+                    dir,
+                    depends,
+                    command,
+                }),
+                _,
+            ) => command_handlers::add_task(workspace, name, dir, depends, command)?,
+            (Some(TaskOperation::Remove { workspace, name }), _) => {
+                command_handlers::remove_task(workspace, name)?
+            }
+            (None, Some(workspace)) => command_handlers::print_tasks(workspace)?,
+            (None, None) => {
+                eprintln!("Give the workspace to list the tasks of with -w");
+                return Err(errors::Error::InvalidPath(String::from("No workspace")).into());
+            }
+        },
+        Operation::Up {
+            workspace,
+            services,
+        } => command_handlers::services_up(workspace, services)?,
+        Operation::Down {
+            workspace,
+            services,
+            timeout,
+        } => command_handlers::services_down(workspace, services, timeout)?,
+        Operation::Ps { workspace } => command_handlers::print_processes(workspace)?,
+        Operation::Logs {
+            workspace,
+            service,
+            follow,
+            lines,
+        } => command_handlers::service_logs(workspace, service, lines, follow)?,
+        Operation::Services {
+            workspace,
+            service_operation,
+        } => match (service_operation, workspace) {
+            (
+                Some(ServiceOperation::Add {
+                    workspace,
+                    name,
+                    dir,
+                    restart,
+                    command,
+                }),
+                _,
+            ) => command_handlers::add_service(workspace, name, dir, restart, command)?,
+            (
+                Some(ServiceOperation::Import {
+                    workspace,
+                    dir,
+                    file,
+                    restart,
+                }),
+                _,
+            ) => command_handlers::import_procfile(workspace, dir, file, restart)?,
+            (Some(ServiceOperation::Remove { workspace, name }), _) => {
+                command_handlers::remove_service(workspace, name)?
+            }
+            (None, Some(workspace)) => command_handlers::print_services(workspace)?,
+            (None, None) => {
+                eprintln!("Give the workspace to list the services of with -w");
+                return Err(errors::Error::InvalidPath(String::from("No workspace")).into());
+            }
+        },
+        Operation::Supervise {
+            log,
+            restart,
+            script,
+        } => std::process::exit(services::supervise(&log, &script, restart)),
+        Operation::Branch {
+            workspace,
+            branch,
+            location,
+            remove,
+            force,
+        } => match remove {
+            true => command_handlers::remove_branch_workspace(workspace, branch, force)?,
+            false => command_handlers::create_branch_workspace(workspace, branch, location)?,
+        },
+        Operation::Browse {
+            workspace,
+            dir,
+            pull_requests,
+            issues,
+            branch,
+            print,
+        } => command_handlers::browse(workspace, dir, pull_requests, issues, branch, print)?,
+        Operation::Log {
+            workspace,
+            all,
+            since,
+            until,
+            author,
+            format,
+            summary,
+        } => {
+            let filter = commits::Filter {
+                since: Some(since),
+                until,
+                author,
+            };
+            command_handlers::print_log(workspace, all, filter, format, summary)?
+        }
+        Operation::Snapshot {
+            workspace,
+            name,
+            snapshot_operation,
+        } => match (snapshot_operation, workspace) {
+            (Some(SnapshotOperation::Restore { workspace, name }), _) => {
+                command_handlers::restore_snapshot(workspace, name)?
+            }
+            (Some(SnapshotOperation::List { workspace: list }), workspace) => {
+                command_handlers::print_snapshots(list.or(workspace))?
+            }
+            (Some(SnapshotOperation::Delete { workspace, name }), _) => {
+                command_handlers::delete_snapshot(workspace, name)?
+            }
+            (None, Some(workspace)) => command_handlers::save_snapshot(workspace, name)?,
+            (None, None) => {
+                eprintln!("Give the workspace to snapshot with -w");
+                return Err(errors::Error::InvalidPath(String::from("No workspace")).into());
+            }
+        },
+        Operation::Clone {
+            workspace,
+            root,
+            lazy,
+            jobs,
+        } => command_handlers::clone_workspace(workspace, root, lazy, jobs)?,
+        Operation::Config { config_operation } => match config_operation {
+            None => command_handlers::print_settings()?,
+            Some(ConfigOperation::Set { name, value }) => {
+                command_handlers::set_setting(name, value)?
+            }
+            Some(ConfigOperation::Unset { name }) => command_handlers::unset_setting(name)?,
+        },
+        Operation::Scan {
+            root,
+            depth,
+            markers,
+            workspace,
+            yes,
+        } => command_handlers::scan(root, depth, markers, workspace, yes)?,
+        Operation::SyncProjects { workspace } => command_handlers::sync_projects(workspace)?,
         Operation::Dir {
             workspace,
             dir_operation,
         } => match dir_operation {
-            DirOperation::Add { path } => {
-                command_handlers::add_dir_to_workspace(workspace, path)?;
+            DirOperation::Add {
+                path,
+                label,
+                path_options,
+            } => {
+                command_handlers::add_dir_to_workspace(workspace, path, label, path_options)?;
+            }
+            DirOperation::Del { dir } => {
+                command_handlers::remove_dir_from_workspace(workspace, dir)?;
+            }
+            DirOperation::Label { dir, label } => {
+                command_handlers::label_dir(workspace, dir, label)?;
             }
-            DirOperation::Del => {
-                command_handlers::remove_dir_from_workspace(workspace)?;
+            DirOperation::Reorder { dirs } => {
+                command_handlers::reorder_dirs(workspace, dirs)?;
+            }
+            DirOperation::Primary { dir } => {
+                command_handlers::set_primary_dir(workspace, dir)?;
             }
             DirOperation::Init => {
             }
@@ -115,3 +1143,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn help(args: &[&str]) -> String {
+        match Options::clap().get_matches_from_safe(args) {
+            Err(err) => err.message,
+            Ok(_) => panic!("{:?} printed no help", args),
+        }
+    }
+
+    #[test]
+    fn add_subcommands_keep_their_about() {
+        assert!(help(&["ws", "--help"]).contains("Add new workspace"));
+        assert!(help(&["ws", "add", "--help"]).contains("Add new workspace"));
+        assert!(help(&["ws", "dir", "-w", "x", "--help"])
+            .contains("about add a directory to a workspace"));
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeSet;
+
+use crate::db::WorkspaceWrite;
+use crate::workspace::{Dir, Workspace};
+
+/// Difference between a declared workspace and the stored one,
+/// workspaces are identified by name and directories by path
+#[derive(Debug)]
+pub enum Diff<'a> {
+    Create(&'a Workspace),
+    Update {
+        desired: &'a Workspace,
+        changes: Vec<String>,
+    },
+    /// Stored but not declared, only with `prune`
+    Delete(&'a Workspace),
+}
+
+impl<'a> Diff<'a> {
+    pub fn to_write(&self) -> WorkspaceWrite<'a> {
+        match self {
+            Diff::Create(desired) => WorkspaceWrite::Create(desired),
+            Diff::Update { desired, .. } => WorkspaceWrite::Update(desired),
+            Diff::Delete(current) => WorkspaceWrite::Delete(&current.name),
+        }
+    }
+}
+
+/// What has to change so the stored workspaces `current` match `desired`,
+/// undeclared workspaces are only deleted with `prune`
+pub fn diff<'a>(desired: &'a [Workspace], current: &'a [Workspace], prune: bool) -> Vec<Diff<'a>> {
+    let mut diffs = vec![];
+
+    for ws in desired {
+        match current.iter().find(|c| c.name == ws.name) {
+            None => diffs.push(Diff::Create(ws)),
+            Some(stored) => {
+                let changes = changes(stored, ws);
+                if !changes.is_empty() {
+                    diffs.push(Diff::Update {
+                        desired: ws,
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+
+    if prune {
+        diffs.extend(
+            current
+                .iter()
+                .filter(|c| !desired.iter().any(|ws| ws.name == c.name))
+                .map(Diff::Delete),
+        );
+    }
+
+    diffs
+}
+
+/// Workspaces of `current` which are not in `desired`
+pub fn undeclared<'a>(desired: &[Workspace], current: &'a [Workspace]) -> Vec<&'a str> {
+    current
+        .iter()
+        .filter(|c| !desired.iter().any(|ws| ws.name == c.name))
+        .map(|c| c.name.as_str())
+        .collect()
+}
+
+/// Human readable changes from `stored` to `desired`, one per line
+fn changes<'a>(stored: &'a Workspace, desired: &'a Workspace) -> Vec<String> {
+    let mut changes = vec![];
+    let editor = |editor: &Option<String>| editor.clone().unwrap_or(String::from("(default)"));
+
+    if stored.editor != desired.editor {
+        changes.push(format!(
+            "~ editor {} -> {}",
+            editor(&stored.editor),
+            editor(&desired.editor)
+        ));
+    }
+
+    let names: BTreeSet<&String> = stored.env.keys().chain(desired.env.keys()).collect();
+    for name in names {
+        match (stored.env.get(name), desired.env.get(name)) {
+            (None, Some(value)) => changes.push(format!("+ env {}={}", name, value)),
+            (Some(_), None) => changes.push(format!("- env {}", name)),
+            (Some(old), Some(new)) if old != new => {
+                changes.push(format!("~ env {}: {} -> {}", name, old, new))
+            }
+            _ => {}
+        }
+    }
+
+    for dir in stored.dir_iter() {
+        if desired.check_dir_already_exists(&dir.path).is_none() {
+            changes.push(format!("- dir {}", dir.path.display()));
+        }
+    }
+
+    for dir in desired.dir_iter() {
+        let old = stored.dir_iter().find(|d| d.path == dir.path);
+        match old {
+            None => changes.push(format!("+ dir {}{}", dir.path.display(), label(dir))),
+            Some(old) => changes.extend(dir_changes(old, dir)),
+        }
+    }
+
+    let kept = |ws: &'a Workspace, other: &Workspace| -> Vec<&'a Dir> {
+        ws.dir_iter()
+            .filter(|d| other.check_dir_already_exists(&d.path).is_some())
+            .collect()
+    };
+    let (before, after) = (kept(stored, desired), kept(desired, stored));
+    if before
+        .iter()
+        .zip(&after)
+        .any(|(old, new)| old.path != new.path)
+    {
+        let names: Vec<_> = after.iter().map(|d| d.display_name()).collect();
+        changes.push(format!("~ order {}", names.join(", ")));
+    }
+
+    changes
+}
+
+fn dir_changes(old: &Dir, new: &Dir) -> Vec<String> {
+    let path = new.path.display();
+    let mut changes = vec![];
+
+    if old.label != new.label {
+        changes.push(format!(
+            "~ dir {}: label {} -> {}",
+            path,
+            old.label.as_deref().unwrap_or("(none)"),
+            new.label.as_deref().unwrap_or("(none)")
+        ));
+    }
+    if old.init != new.init {
+        changes.push(format!("~ dir {}: script changed", path));
+    }
+    if old.primary != new.primary {
+        let primary = if new.primary {
+            "primary"
+        } else {
+            "not primary"
+        };
+        changes.push(format!("~ dir {}: {}", path, primary));
+    }
+
+    changes
+}
+
+fn label(dir: &Dir) -> String {
+    match &dir.label {
+        Some(label) => format!(" [{}]", label),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str, dirs: &[(&str, Option<&str>)]) -> Workspace {
+        let mut ws = Workspace::new(String::from(name));
+        for (path, label) in dirs {
+            let mut dir = Dir::new(*path);
+            dir.label = label.map(String::from);
+            ws.add_dir(dir);
+        }
+        ws
+    }
+
+    #[test]
+    fn diffs_workspaces_by_name_and_dirs_by_path() {
+        let current = vec![
+            workspace("a", &[("/a", Some("api")), ("/b", None), ("/c", None)]),
+            workspace("old", &[("/old", None)]),
+        ];
+        let mut a = workspace("a", &[("/c", None), ("/a", Some("backend")), ("/d", None)]);
+        a.env.insert(String::from("K"), String::from("v"));
+        let desired = vec![a, workspace("new", &[("/new", None)])];
+
+        let diffs = diff(&desired, &current, false);
+
+        assert_eq!(diffs.len(), 2);
+        match &diffs[0] {
+            Diff::Update { changes, .. } => assert_eq!(
+                changes,
+                &[
+                    "+ env K=v",
+                    "- dir /b",
+                    "~ dir /a: label api -> backend",
+                    "+ dir /d",
+                    "~ order /c, backend"
+                ]
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(diffs[1], Diff::Create(ws) if ws.name == "new"));
+        assert_eq!(undeclared(&desired, &current), vec!["old"]);
+
+        let pruned = diff(&desired, &current, true);
+        assert!(matches!(pruned[2], Diff::Delete(ws) if ws.name == "old"));
+    }
+
+    #[test]
+    fn no_diff_for_matching_workspaces() {
+        let current = vec![workspace("a", &[("/a", Some("api"))])];
+        let desired = vec![workspace("a", &[("/a", Some("api"))])];
+
+        assert!(diff(&desired, &current, true).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+
+use crate::utils;
+
+/// A directory to run a command in
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// Short name the output lines are prefixed with
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Success,
+    /// Exit code, `None` when killed by a signal
+    Failed(Option<i32>),
+    /// The command could not be started
+    Error(String),
+    /// Not started because an earlier command failed
+    NotRun,
+}
+
+impl Outcome {
+    pub fn is_success(&self) -> bool {
+        *self == Outcome::Success
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Outcome::Success => String::from("ok"),
+            Outcome::Failed(Some(code)) => format!("failed with exit code {}", code),
+            Outcome::Failed(None) => String::from("killed"),
+            Outcome::Error(err) => err.clone(),
+            Outcome::NotRun => String::from("not run"),
+        }
+    }
+}
+
+/// How the output of the commands is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    /// Streamed line by line, each line prefixed with the job name
+    Prefixed,
+    /// Collected and shown at once below the job name when a job finishes
+    Grouped,
+}
+
+/// Runs the command built by `command` from the index and the job for every
+/// job on up to `threads` threads, showing the output as `output` says.
+/// Without `continue_on_error` no further jobs start once one failed
+pub fn run_all<F>(
+    jobs: &[Job],
+    threads: usize,
+    continue_on_error: bool,
+    output: Output,
+    command: F,
+) -> Vec<(Outcome, Duration)>
+where
+    F: Fn(usize, &Job) -> Command + Sync,
+{
+    let width = jobs.iter().map(|job| job.name.len()).max().unwrap_or(0);
+    let failed = AtomicBool::new(false);
+    // keeps the groups of jobs finishing together apart
+    let print_lock = Mutex::new(());
+
+    let indexed: Vec<(usize, &Job)> = jobs.iter().enumerate().collect();
+    utils::parallel_map(&indexed, threads, |&(index, job)| {
+        if failed.load(Ordering::SeqCst) && !continue_on_error {
+            return (Outcome::NotRun, Duration::ZERO);
+        }
+
+        let start = Instant::now();
+        let outcome = match output {
+            Output::Prefixed => {
+                let prefix = format!("{:width$} |", job.name, width = width);
+                run_prefixed(command(index, job), &prefix)
+            }
+            Output::Grouped => run_grouped(command(index, job), &job.name, &print_lock),
+        };
+        if !outcome.is_success() {
+            failed.store(true, Ordering::SeqCst);
+        }
+
+        (outcome, start.elapsed())
+    })
+}
+
+/// Runs `command`, printing its stdout and stderr line by line after `prefix`
+fn run_prefixed(mut command: Command, prefix: &str) -> Outcome {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => return Outcome::Error(format!("cannot start: {}", err)),
+    };
+
+    let prefix = prefix.cyan().to_string();
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    thread::scope(|scope| {
+        scope.spawn(|| print_lines(stdout, &prefix, false));
+        scope.spawn(|| print_lines(stderr, &prefix, true));
+    });
+
+    match child.wait() {
+        Ok(status) if status.success() => Outcome::Success,
+        Ok(status) => Outcome::Failed(status.code()),
+        Err(err) => Outcome::Error(err.to_string()),
+    }
+}
+
+/// Runs `command` and prints its whole output below a `name` header at once
+fn run_grouped(mut command: Command, name: &str, print_lock: &Mutex<()>) -> Outcome {
+    let output = command.stdin(Stdio::null()).output();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => return Outcome::Error(format!("cannot start: {}", err)),
+    };
+
+    let outcome = match output.status.success() {
+        true => Outcome::Success,
+        false => Outcome::Failed(output.status.code()),
+    };
+
+    let _guard = print_lock.lock().unwrap_or_else(|err| err.into_inner());
+    println!("{}", format!("── {} ──", name).cyan());
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+    outcome
+}
+
+fn print_lines<R: Read>(reader: R, prefix: &str, to_stderr: bool) {
+    for line in BufReader::new(reader).split(b'\n').map_while(Result::ok) {
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+        match to_stderr {
+            true => eprintln!("{} {}", prefix, line),
+            false => println!("{} {}", prefix, line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{init_repo, run_git};
+
+    #[test]
+    fn runs_in_every_directory_and_stops_after_a_failure() {
+        let root = tempfile::tempdir().unwrap();
+        init_repo(&root.path().join("seed"));
+        run_git(
+            root.path(),
+            &["clone", "-q", "--bare", "seed", "remote.git"],
+        );
+        run_git(root.path(), &["clone", "-q", "remote.git", "api"]);
+        run_git(root.path(), &["clone", "-q", "remote.git", "web"]);
+        std::fs::create_dir(root.path().join("plain")).unwrap();
+
+        let jobs: Vec<Job> = ["api", "plain", "web"]
+            .iter()
+            .map(|name| Job {
+                name: String::from(*name),
+                dir: root.path().join(name),
+            })
+            .collect();
+        let pull = |_, job: &Job| {
+            let mut command = Command::new("git");
+            command
+                .args(["pull", "-q", "--ff-only"])
+                .current_dir(&job.dir);
+            command
+        };
+
+        let outcomes: Vec<Outcome> = run_all(&jobs, 4, true, Output::Prefixed, pull)
+            .into_iter()
+            .map(|(outcome, _)| outcome)
+            .collect();
+        assert_eq!(outcomes[0], Outcome::Success);
+        assert!(matches!(outcomes[1], Outcome::Failed(Some(_))));
+        assert_eq!(outcomes[2], Outcome::Success);
+
+        let stopped = run_all(&jobs, 1, false, Output::Grouped, pull);
+        assert_eq!(stopped[2].0, Outcome::NotRun);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::errors::Error;
+
+/// Name of the file a repository describes itself with
+pub const FILE_NAME: &str = ".ws.toml";
+
+/// Contents of a `.ws.toml` project file
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectFile {
+    /// Workspace the repository is added to when no name is given
+    pub workspace: Option<String>,
+    pub label: Option<String>,
+    /// Init script of the directory
+    pub script: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Named commands run in the directory
+    #[serde(default)]
+    pub tasks: BTreeMap<String, TaskDef>,
+    /// Repositories belonging to the same workspace, relative to this one
+    #[serde(default)]
+    pub siblings: Vec<String>,
+}
+
+/// A task of a project file, only its command or with the tasks it needs
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum TaskDef {
+    Command(String),
+    Spec(TaskSpec),
+}
+
+/// `{ command = "cargo build", depends = ["lint", "api:build"] }`
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskSpec {
+    pub command: String,
+    /// Tasks run first, `task` in the same directory or `dir:task`
+    #[serde(default)]
+    pub depends: Vec<String>,
+}
+
+impl TaskDef {
+    pub fn command(&self) -> &str {
+        match self {
+            TaskDef::Command(command) => command,
+            TaskDef::Spec(spec) => &spec.command,
+        }
+    }
+
+    pub fn depends(&self) -> &[String] {
+        match self {
+            TaskDef::Command(_) => &[],
+            TaskDef::Spec(spec) => &spec.depends,
+        }
+    }
+}
+
+impl ProjectFile {
+    pub fn parse(text: &str) -> Result<ProjectFile, Error> {
+        toml::from_str(text)
+            .map_err(|err| Error::InvalidFile(format!("Invalid {}: {}", FILE_NAME, err)))
+    }
+
+    /// Everything the file would run, the user trusts exactly this text.
+    /// `None` when the file runs nothing
+    pub fn scripts(&self) -> Option<String> {
+        let lines: Vec<String> = self
+            .script
+            .iter()
+            .map(|script| format!("script: {}", script))
+            .chain(
+                self.tasks
+                    .iter()
+                    .map(|(name, task)| format!("task {}: {}", name, task.command())),
+            )
+            .collect();
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    /// Absolute paths of the siblings of the repository in `dir`
+    pub fn sibling_paths(&self, dir: &Path) -> Vec<PathBuf> {
+        self.siblings
+            .iter()
+            .map(|sibling| crate::utils::normalize(&dir.join(sibling)))
+            .collect()
+    }
+}
+
+/// Reads the project file of `dir` as `(text, parsed)`, `None` without one
+pub fn read(dir: &Path) -> Result<Option<(String, ProjectFile)>, Error> {
+    match fs::read_to_string(dir.join(FILE_NAME)) {
+        Ok(text) => {
+            let project = ProjectFile::parse(&text)?;
+            Ok(Some((text, project)))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::Io(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_project_files() {
+        let project = ProjectFile::parse(
+            r#"
+            workspace = "shop"
+            label = "api"
+            script = "cargo build"
+            siblings = ["../web"]
+
+            [env]
+            RUST_LOG = "debug"
+
+            [tasks]
+            test = "cargo test"
+            build = { command = "cargo build", depends = ["test", "web:build"] }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(project.workspace.as_deref(), Some("shop"));
+        assert_eq!(project.env["RUST_LOG"], "debug");
+        assert_eq!(
+            project.scripts().unwrap(),
+            "script: cargo build\ntask build: cargo build\ntask test: cargo test"
+        );
+        assert_eq!(project.tasks["build"].depends(), ["test", "web:build"]);
+        assert_eq!(
+            project.sibling_paths(Path::new("/code/api")),
+            vec![PathBuf::from("/code/web")]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(ProjectFile::parse("lable = \"api\"").is_err());
+        assert_eq!(ProjectFile::parse("").unwrap().scripts(), None);
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Named directory paths are stored relative to,
+/// e.g. `@code = ~/code` stores `~/code/api` as `@code/api`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Checks that `name` can be used as `@name` in a stored path
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Rewrites absolute `path` relative to the most specific root
+/// or the home directory, other paths are returned as is
+pub fn to_portable(path: &Path, roots: &[Root], home: Option<&Path>) -> PathBuf {
+    let mut best: Option<(usize, PathBuf)> = None;
+
+    let candidates = roots
+        .iter()
+        .map(|root| (format!("@{}", root.name), root.path.as_path()))
+        .chain(home.map(|home| (String::from("~"), home)));
+
+    for (prefix, base) in candidates {
+        if let Ok(rest) = path.strip_prefix(base) {
+            let depth = base.components().count();
+            if best
+                .as_ref()
+                .is_none_or(|(best_depth, _)| depth > *best_depth)
+            {
+                let portable = match rest.as_os_str().is_empty() {
+                    true => PathBuf::from(&prefix),
+                    false => Path::new(&prefix).join(rest),
+                };
+                best = Some((depth, portable));
+            }
+        }
+    }
+
+    match best {
+        Some((_, portable)) => portable,
+        None => path.to_path_buf(),
+    }
+}
+
+/// Reverse of [`to_portable`], paths with an unknown root are returned as is
+pub fn from_portable(path: &Path, roots: &[Root], home: Option<&Path>) -> PathBuf {
+    let mut components = path.components();
+
+    let base = match components.next() {
+        Some(Component::Normal(first)) if first == "~" => home,
+        Some(Component::Normal(first)) => first
+            .to_str()
+            .and_then(|first| first.strip_prefix('@'))
+            .and_then(|name| roots.iter().find(|root| root.name == name))
+            .map(|root| root.path.as_path()),
+        _ => None,
+    };
+
+    match base {
+        Some(base) => base.join(components.as_path()),
+        None => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    fn roots() -> Vec<Root> {
+        vec![
+            Root {
+                name: String::from("code"),
+                path: PathBuf::from("/home/alice/code"),
+            },
+            Root {
+                name: String::from("work"),
+                path: PathBuf::from("/mnt/work"),
+            },
+        ]
+    }
+
+    #[test]
+    fn stores_paths_relative_to_most_specific_root() {
+        let home = Some(Path::new("/home/alice"));
+        let portable = |p: &str| to_portable(Path::new(p), &roots(), home);
+
+        assert_eq!(portable("/home/alice/code/api"), PathBuf::from("@code/api"));
+        assert_eq!(portable("/home/alice/notes"), PathBuf::from("~/notes"));
+        assert_eq!(portable("/mnt/work"), PathBuf::from("@work"));
+        assert_eq!(portable("/mnt/workshop"), PathBuf::from("/mnt/workshop"));
+        assert_eq!(portable("/srv/api"), PathBuf::from("/srv/api"));
+    }
+
+    #[test]
+    fn resolves_portable_paths() {
+        let home = Some(Path::new("/home/bob"));
+        let resolve = |p: &str| from_portable(Path::new(p), &roots(), home);
+
+        assert_eq!(resolve("@code/api"), PathBuf::from("/home/alice/code/api"));
+        assert_eq!(resolve("~/notes"), PathBuf::from("/home/bob/notes"));
+        assert_eq!(resolve("@work"), PathBuf::from("/mnt/work"));
+        assert_eq!(resolve("@unknown/api"), PathBuf::from("@unknown/api"));
+        assert_eq!(resolve("/srv/api"), PathBuf::from("/srv/api"));
+    }
+
+    #[test]
+    fn validates_root_names() {
+        assert!(is_valid_name("code-2_x"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("co/de"));
+    }
+}
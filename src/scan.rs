@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+/// File or directory marking a project root when no marker is given
+pub const DEFAULT_MARKER: &str = ".git";
+
+/// Project roots below `root`, directories containing one of `markers`.
+/// Ignored and hidden directories are skipped, so are projects nested in
+/// a project, `depth` limits how deep below `root` is searched
+pub fn find_projects(root: &Path, depth: Option<usize>, markers: &[String]) -> Vec<PathBuf> {
+    let owned = markers.to_vec();
+    let walker = WalkBuilder::new(root)
+        .max_depth(depth)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            // projects are not searched for further projects
+            let in_project = entry.depth() > 0
+                && entry
+                    .path()
+                    .parent()
+                    .is_some_and(|parent| is_project(parent, &owned));
+
+            is_dir && !in_project
+        })
+        .build();
+
+    let mut projects: Vec<PathBuf> = walker
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|dir| is_project(dir, markers))
+        .collect();
+    projects.sort();
+
+    projects
+}
+
+fn is_project(dir: &Path, markers: &[String]) -> bool {
+    markers.iter().any(|marker| dir.join(marker).exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn finds_projects_by_marker_respecting_ignore_files() {
+        let root = tempfile::tempdir().unwrap();
+        let mk = |dir: &str| fs::create_dir_all(root.path().join(dir)).unwrap();
+        let touch = |file: &str| fs::write(root.path().join(file), "").unwrap();
+
+        mk("api/.git");
+        mk("api/vendor/lib/.git");
+        mk("group/web/.git");
+        mk("group/tool");
+        touch("group/tool/Cargo.toml");
+        mk("archive/old/.git");
+        fs::write(root.path().join(".ignore"), "archive/\n").unwrap();
+        mk(".hidden/secret/.git");
+
+        let git = [String::from(".git")];
+        assert_eq!(
+            find_projects(root.path(), None, &git),
+            vec![root.path().join("api"), root.path().join("group/web")]
+        );
+        assert_eq!(
+            find_projects(root.path(), Some(1), &git),
+            vec![root.path().join("api")]
+        );
+
+        let both = [String::from(".git"), String::from("Cargo.toml")];
+        assert_eq!(find_projects(root.path(), None, &both).len(), 3);
+    }
+}
@@ -0,0 +1,470 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::Error;
+use crate::utils;
+use crate::workspace::{self, Workspace};
+
+/// File services are read from by `ws services import`
+pub const PROCFILE: &str = "Procfile";
+
+/// A background process of a workspace like a dev server or a database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Service {
+    pub name: String,
+    /// Directory the service runs in, `None` for the primary directory
+    pub dir_id: Option<i32>,
+    pub command: String,
+    /// Started again when it exits with an error
+    pub restart: bool,
+    /// Process group of the running service, `None` when it was not started
+    pub pid: Option<i32>,
+    /// When the service was started, `YYYY-MM-DD HH:MM:SS` in UTC
+    pub started: Option<String>,
+}
+
+impl Service {
+    /// Checks if the supervisor started for the service still runs, `log`
+    /// is the log file of the service
+    pub fn is_running(&self, log: &Path) -> bool {
+        self.pid.is_some() && supervisor(log) == self.pid
+    }
+}
+
+/// Services of a Procfile as `(name, command)`, one `name: command` per line
+pub fn parse_procfile(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| match line.split_once(':') {
+            Some((name, command)) if is_valid_name(name.trim()) && !command.trim().is_empty() => {
+                Ok((name.trim().to_string(), command.trim().to_string()))
+            }
+            _ => Err(format!("Line {} is not `name: command`: {}", number, line)),
+        })
+        .collect()
+}
+
+/// Names of services are used in file names and on the command line
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// File the output of service `name` of workspace `ws` is appended to
+pub fn log_file(ws: &Workspace, name: &str) -> Result<PathBuf, Error> {
+    let dir = utils::cache_dir()?.join("logs").join(ws.file_name());
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir.join(format!("{}.log", name)))
+}
+
+/// File the supervisor of the service logging to `log` writes its pid to
+/// and keeps locked while it runs
+fn pid_file(log: &Path) -> PathBuf {
+    log.with_extension("pid")
+}
+
+/// Pid of the supervisor of the service logging to `log` while it runs. A
+/// pid alone could belong to any process after a reboot or once reused
+pub fn supervisor(log: &Path) -> Option<i32> {
+    let mut file = File::open(pid_file(log)).ok()?;
+    if !is_locked(&file) {
+        return None;
+    }
+
+    let mut pid = String::new();
+    file.read_to_string(&mut pid).ok()?;
+    pid.trim().parse().ok()
+}
+
+/// Starts `service` detached from the terminal in `dir`, supervised by
+/// `ws supervise` in a new process group. Returns the process group
+pub fn start(
+    service: &Service,
+    dir: &Path,
+    env: &BTreeMap<String, String>,
+    log: &Path,
+) -> io::Result<i32> {
+    let output = OpenOptions::new().create(true).append(true).open(log)?;
+
+    let mut command = Command::new(env::current_exe()?);
+    command.arg("supervise").arg("--log").arg(log);
+    if service.restart {
+        command.arg("--restart");
+    }
+    command
+        .arg("--")
+        .arg(&service.command)
+        .current_dir(dir)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(output.try_clone()?)
+        .stderr(output);
+    detach(&mut command)?;
+
+    // the supervisor is not waited for, it outlives ws
+    let mut child = command.spawn()?;
+    let pid = child.id() as i32;
+
+    // only a supervisor holding its pid file counts as running
+    let start = Instant::now();
+    while supervisor(log) != Some(pid) {
+        if child.try_wait()?.is_some() || start.elapsed() > Duration::from_secs(5) {
+            return Err(io::Error::other(
+                "the supervisor did not start, see the log",
+            ));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    Ok(pid)
+}
+
+/// Runs `script` in the current directory with its output appended to
+/// `log`, starting it again after a failure when `restart` is set. Waits
+/// longer between restarts the faster the service keeps failing.
+/// Returns the exit code of the last run
+pub fn supervise(log: &Path, script: &str, restart: bool) -> i32 {
+    // released by the system however the supervisor ends
+    let _pid_file = match lock_pid_file(&pid_file(log), std::process::id() as i32) {
+        Ok(file) => file,
+        Err(err) => {
+            let _ = append(log, &format!("[ws] cannot lock the pid file: {}", err));
+            return 1;
+        }
+    };
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        let started = Instant::now();
+        let status = open_log(log).and_then(|(out, err)| {
+            workspace::script_command(Path::new("."), script, &BTreeMap::new())
+                .stdin(Stdio::null())
+                .stdout(out)
+                .stderr(err)
+                .status()
+        });
+
+        let code = match status {
+            Ok(status) if status.success() => return 0,
+            Ok(status) => status.code(),
+            Err(err) => {
+                let _ = append(log, &format!("[ws] cannot start {}: {}", script, err));
+                return 1;
+            }
+        };
+
+        let exited = match code {
+            Some(code) => format!("exited with code {}", code),
+            None => String::from("was killed"),
+        };
+        if !restart {
+            let _ = append(log, &format!("[ws] {} {}", script, exited));
+            return code.unwrap_or(1);
+        }
+
+        // a service which ran for a while crashed, not failed to start
+        if started.elapsed() > Duration::from_secs(30) {
+            backoff = Duration::from_secs(1);
+        }
+        let _ = append(
+            log,
+            &format!(
+                "[ws] {} {}, restarting in {}s",
+                script,
+                exited,
+                backoff.as_secs()
+            ),
+        );
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
+
+fn open_log(log: &Path) -> io::Result<(File, File)> {
+    let out = OpenOptions::new().create(true).append(true).open(log)?;
+    let err = out.try_clone()?;
+
+    Ok((out, err))
+}
+
+fn append(log: &Path, line: &str) -> io::Result<()> {
+    let (mut out, _) = open_log(log)?;
+    writeln!(out, "{}", line)
+}
+
+/// How [`stop`] ended a service
+#[derive(Debug, PartialEq)]
+pub enum Stopped {
+    /// Exited after SIGTERM
+    Terminated,
+    /// Still running after the timeout and killed with SIGKILL
+    Killed,
+    /// Had already exited
+    NotRunning,
+}
+
+/// Stops process group `pid` of the service logging to `log` with SIGTERM,
+/// followed by SIGKILL when it is still running after `timeout`. Nothing is
+/// signaled unless `pid` is still the supervisor of the service
+pub fn stop(pid: i32, log: &Path, timeout: Duration) -> io::Result<Stopped> {
+    if supervisor(log) != Some(pid) {
+        return Ok(Stopped::NotRunning);
+    }
+
+    signal(pid, Signal::Term)?;
+
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if !is_alive(pid) {
+            return Ok(Stopped::Terminated);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    signal(pid, Signal::Kill)?;
+
+    Ok(Stopped::Killed)
+}
+
+enum Signal {
+    Term,
+    Kill,
+}
+
+#[cfg(unix)]
+fn detach(command: &mut Command) -> io::Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    // Ctrl-C in the terminal only reaches its foreground process group
+    command.process_group(0);
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn detach(_command: &mut Command) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "services are only supported on unix",
+    ))
+}
+
+/// Writes `pid` to `path` and locks it for as long as the returned file is
+/// open, fails when another process holds the lock
+#[cfg(unix)]
+fn lock_pid_file(path: &Path, pid: i32) -> io::Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // truncated only once locked, the pid of a running supervisor is kept
+    file.set_len(0)?;
+    writeln!(file, "{}", pid)?;
+    file.flush()?;
+
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn lock_pid_file(_path: &Path, _pid: i32) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "services are only supported on unix",
+    ))
+}
+
+/// Checks if another process holds the lock of `file`
+#[cfg(unix)]
+fn is_locked(file: &File) -> bool {
+    use std::os::unix::io::AsRawFd;
+
+    // the shared lock is released again when `file` is closed
+    match unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_SH | libc::LOCK_NB) } {
+        0 => false,
+        _ => io::Error::last_os_error().raw_os_error() == Some(libc::EWOULDBLOCK),
+    }
+}
+
+#[cfg(not(unix))]
+fn is_locked(_file: &File) -> bool {
+    false
+}
+
+/// Checks if any process of process group `pid` is alive
+#[cfg(unix)]
+fn is_alive(pid: i32) -> bool {
+    // signal 0 only checks whether the group could be signaled
+    let result = unsafe { libc::kill(-pid, 0) };
+
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_alive(_pid: i32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn signal(pid: i32, signal: Signal) -> io::Result<()> {
+    let signal = match signal {
+        Signal::Term => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+
+    match unsafe { libc::kill(-pid, signal) } {
+        0 => Ok(()),
+        _ => match io::Error::last_os_error() {
+            // exited in the meantime
+            err if err.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            err => Err(err),
+        },
+    }
+}
+
+#[cfg(not(unix))]
+fn signal(_pid: i32, _signal: Signal) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "services are only supported on unix",
+    ))
+}
+
+/// Prints the last `lines` lines of `log`. With `follow` keeps printing what
+/// is appended until interrupted
+pub fn print_log(log: &Path, lines: usize, follow: bool) -> io::Result<()> {
+    let mut file = File::open(log)?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    print!("{}", last_lines(&text, lines));
+    io::stdout().flush()?;
+
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = file.stream_position()?;
+    loop {
+        thread::sleep(Duration::from_millis(250));
+
+        let length = fs::metadata(log)?.len();
+        // the log was truncated
+        if length < position {
+            position = 0;
+        }
+        if length == position {
+            continue;
+        }
+
+        file.seek(SeekFrom::Start(position))?;
+        let mut appended = vec![];
+        file.read_to_end(&mut appended)?;
+        position += appended.len() as u64;
+
+        io::stdout().write_all(&appended)?;
+        io::stdout().flush()?;
+    }
+}
+
+/// The last `lines` lines of `text`
+fn last_lines(text: &str, lines: usize) -> &str {
+    if lines == 0 {
+        return "";
+    }
+
+    let trimmed = text.strip_suffix('\n').unwrap_or(text);
+    match trimmed.rmatch_indices('\n').nth(lines - 1) {
+        Some((index, _)) => &text[index + 1..],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_procfiles() {
+        let text = "# dev services\nweb: npm run dev -- --port 3000\n\n db :  postgres -D data\n";
+
+        assert_eq!(
+            parse_procfile(text).unwrap(),
+            vec![
+                (
+                    String::from("web"),
+                    String::from("npm run dev -- --port 3000")
+                ),
+                (String::from("db"), String::from("postgres -D data")),
+            ]
+        );
+        assert!(parse_procfile("web npm start").is_err());
+        assert!(parse_procfile("my web: npm start").is_err());
+    }
+
+    #[test]
+    fn keeps_the_last_lines() {
+        assert_eq!(last_lines("a\nb\nc\n", 2), "b\nc\n");
+        assert_eq!(last_lines("a\nb\nc", 2), "b\nc");
+        assert_eq!(last_lines("a\nb\n", 5), "a\nb\n");
+        assert_eq!(last_lines("a\nb\n", 0), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_a_process_group() {
+        use std::os::unix::process::CommandExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("web.log");
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; exec sleep 30"])
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+        // reaps the process so it does not stay around as a zombie
+        let waiter = thread::spawn(move || child.wait());
+        // time for the shell to ignore SIGTERM
+        thread::sleep(Duration::from_millis(200));
+
+        // a process which is not the supervisor is left alone
+        assert_eq!(supervisor(&log), None);
+        assert_eq!(
+            stop(pid, &log, Duration::ZERO).unwrap(),
+            Stopped::NotRunning
+        );
+        assert!(is_alive(pid));
+
+        let lock = lock_pid_file(&pid_file(&log), pid).unwrap();
+        assert_eq!(supervisor(&log), Some(pid));
+        assert!(lock_pid_file(&pid_file(&log), 1).is_err());
+        assert_eq!(
+            stop(pid, &log, Duration::from_millis(300)).unwrap(),
+            Stopped::Killed
+        );
+        waiter.join().unwrap().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(!is_alive(pid));
+
+        drop(lock);
+        assert_eq!(supervisor(&log), None);
+    }
+}
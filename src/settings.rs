@@ -0,0 +1,39 @@
+use crate::browse::HostMapping;
+
+/// Directory the worktrees of `ws branch` are created in
+pub const WORKTREES: &str = "worktrees";
+
+/// Command `ws browse` opens urls with
+pub const BROWSER: &str = "browser";
+
+/// Settings `host.<host>` tell `ws browse` how to link to repositories on
+/// `<host>`, see [`HostMapping`]
+pub const HOST_PREFIX: &str = "host.";
+
+/// Settings `ws config` accepts with a short description
+pub const KNOWN: &[(&str, &str)] = &[
+    (WORKTREES, "directory `ws branch` creates worktrees in"),
+    (
+        BROWSER,
+        "command `ws browse` opens urls with, defaults to the system browser",
+    ),
+];
+
+/// Checks if `name` is a setting ws knows about
+pub fn is_known(name: &str) -> bool {
+    KNOWN.iter().any(|(known, _)| *known == name) || host_of(name).is_some()
+}
+
+/// Host a `host.<host>` setting is about
+pub fn host_of(name: &str) -> Option<&str> {
+    name.strip_prefix(HOST_PREFIX)
+        .filter(|host| !host.is_empty())
+}
+
+/// Checks if `value` is valid for setting `name`
+pub fn check(name: &str, value: &str) -> Result<(), String> {
+    match host_of(name) {
+        Some(_) => value.parse::<HostMapping>().map(|_| ()),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+
+use crate::git;
+use crate::utils;
+use crate::workspace::Workspace;
+
+/// Checked out state of the repositories of a workspace at one point in time
+#[derive(Debug)]
+pub struct Snapshot {
+    pub workspace: String,
+    pub name: String,
+    /// `YYYY-MM-DD HH:MM:SS` in UTC
+    pub created: String,
+    pub repos: Vec<RepoState>,
+}
+
+/// State of one repository in a [`Snapshot`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoState {
+    pub dir_id: i32,
+    pub path: PathBuf,
+    /// Commit checked out
+    pub head: String,
+    /// Branch checked out, `None` on a detached head
+    pub branch: Option<String>,
+    /// Had uncommitted changes, which are not part of the snapshot
+    pub dirty: bool,
+}
+
+impl RepoState {
+    pub fn short_head(&self) -> &str {
+        &self.head[..self.head.len().min(7)]
+    }
+
+    /// Short description like `main@1a2b3c4*`, `*` marks a dirty repository
+    pub fn describe(&self) -> String {
+        format!(
+            "{}@{}{}",
+            self.branch.as_deref().unwrap_or("(detached)"),
+            self.short_head(),
+            if self.dirty { "*" } else { "" }
+        )
+    }
+}
+
+/// Records the state of every repository of `ws`, directories which are
+/// not repositories or have no commit yet are left out
+pub fn capture(ws: &Workspace) -> Vec<RepoState> {
+    let dirs: Vec<_> = ws.dir_iter().collect();
+
+    utils::parallel_map(&dirs, utils::default_threads(), |dir| {
+        let status = git::status(&dir.path)?;
+        let head = git::git(&dir.path, &["rev-parse", "HEAD"])?;
+
+        Some(RepoState {
+            dir_id: dir.id,
+            path: dir.path.clone(),
+            head,
+            dirty: status.is_dirty(),
+            branch: status.branch,
+        })
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Reasons `repo` cannot be restored right now
+pub fn check_restore(repo: &RepoState) -> Option<String> {
+    let path = repo.path.display();
+
+    match git::status(&repo.path) {
+        None => Some(format!("{} is no repository anymore", path)),
+        Some(status) if status.is_dirty() => Some(format!("{} has uncommitted changes", path)),
+        Some(status) if status.operation.is_some() => Some(format!(
+            "{} has a {} in progress",
+            path,
+            status.operation.unwrap()
+        )),
+        Some(_) => {
+            let commit = format!("{}^{{commit}}", repo.head);
+            git::git_checked(&repo.path, &["cat-file", "-e", &commit])
+                .err()
+                .map(|_| format!("{} does not have commit {}, fetch first", path, repo.head))
+        }
+    }
+}
+
+/// Checks out the recorded state of `repo`. A branch which moved on since
+/// is not reset, its recorded commit is checked out detached instead.
+/// Returns a note about what was done
+pub fn restore(repo: &RepoState) -> Result<String, String> {
+    let branch_head = repo.branch.as_ref().and_then(|branch| {
+        git::git(
+            &repo.path,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("refs/heads/{}", branch),
+            ],
+        )
+    });
+
+    match (&repo.branch, branch_head) {
+        (Some(branch), Some(head)) if head == repo.head => {
+            git::git_checked(&repo.path, &["checkout", "--quiet", branch])?;
+            Ok(format!("checked out {}", repo.describe()))
+        }
+        (branch, _) => {
+            git::git_checked(&repo.path, &["checkout", "--quiet", "--detach", &repo.head])?;
+            Ok(match branch {
+                Some(branch) => format!(
+                    "branch {} moved on, checked out {} detached",
+                    branch,
+                    repo.short_head()
+                ),
+                None => format!("checked out {} detached", repo.short_head()),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::git::test_support::{init_repo, run_git};
+    use crate::workspace::Dir;
+
+    #[test]
+    fn restores_branches_and_commits() {
+        let root = tempfile::tempdir().unwrap();
+        let (api, web) = (root.path().join("api"), root.path().join("web"));
+        init_repo(&api);
+        init_repo(&web);
+        let mut ws = Workspace::new(String::from("x"));
+        ws.add_dir(Dir::new(&api));
+        ws.add_dir(Dir::new(&web));
+        ws.add_dir(Dir::new(root.path()));
+
+        let repos = capture(&ws);
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].describe(), format!("main@{}", &repos[0].head[..7]));
+
+        run_git(&api, &["switch", "-q", "-c", "feature"]);
+        run_git(&web, &["commit", "-q", "--allow-empty", "-m", "moved"]);
+        fs::write(api.join("README"), "changed").unwrap();
+
+        assert!(check_restore(&repos[0]).unwrap().contains("uncommitted"));
+        assert_eq!(check_restore(&repos[1]), None);
+
+        run_git(&api, &["checkout", "-q", "README"]);
+        assert_eq!(check_restore(&repos[0]), None);
+
+        assert!(restore(&repos[0]).unwrap().starts_with("checked out main@"));
+        assert!(restore(&repos[1])
+            .unwrap()
+            .starts_with("branch main moved on"));
+        assert_eq!(git::status(&api).unwrap().branch.as_deref(), Some("main"));
+        assert_eq!(git::status(&web).unwrap().branch, None);
+    }
+}
@@ -0,0 +1,188 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::errors::Error;
+use crate::vscode;
+
+/// Another tool whose list of projects can be imported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Recently opened folders of VS Code
+    VscodeRecent,
+    /// `projectile-bookmarks.eld` of Emacs projectile
+    Projectile,
+    /// History of project.nvim
+    Nvim,
+    /// Repositories cloned with ghq
+    Ghq,
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::VscodeRecent => "VS Code recent folders",
+            Source::Projectile => "projectile bookmarks",
+            Source::Nvim => "project.nvim history",
+            Source::Ghq => "ghq",
+        }
+    }
+
+    /// Files the tool keeps its list in, the first existing one is read
+    fn default_files(&self) -> Vec<PathBuf> {
+        let home = dirs::home_dir();
+
+        match self {
+            Source::VscodeRecent => vscode::recent_files(),
+            Source::Projectile => home
+                .iter()
+                .flat_map(|home| {
+                    [
+                        home.join(".emacs.d").join("projectile-bookmarks.eld"),
+                        home.join(".config")
+                            .join("emacs")
+                            .join("projectile-bookmarks.eld"),
+                    ]
+                })
+                .collect(),
+            Source::Nvim => {
+                // stdpath("data") of neovim
+                let data = match env::var_os("XDG_DATA_HOME") {
+                    Some(data) => Some(PathBuf::from(data).join("nvim")),
+                    None if cfg!(windows) => dirs::data_local_dir().map(|d| d.join("nvim-data")),
+                    None => home.map(|home| home.join(".local").join("share").join("nvim")),
+                };
+                data.map(|data| data.join("project_nvim").join("project_history"))
+                    .into_iter()
+                    .collect()
+            }
+            Source::Ghq => vec![],
+        }
+    }
+}
+
+/// Directories listed by `source`, read from `file` or where the tool keeps them.
+/// Paths are returned as listed, they may not exist anymore
+pub fn read(source: Source, file: Option<&Path>) -> Result<Vec<PathBuf>, Error> {
+    if source == Source::Ghq {
+        return match file {
+            Some(file) => Ok(parse_lines(&fs::read_to_string(file)?)),
+            None => ghq_list(),
+        };
+    }
+
+    let file = match file {
+        Some(file) => file.to_path_buf(),
+        None => source
+            .default_files()
+            .into_iter()
+            .find(|file| file.is_file())
+            .ok_or_else(|| {
+                Error::InvalidFile(format!(
+                    "No {} found, give the file with --file",
+                    source.name()
+                ))
+            })?,
+    };
+
+    match source {
+        Source::VscodeRecent => vscode::recent_folders(&file),
+        Source::Projectile => Ok(parse_projectile(
+            &fs::read_to_string(&file)?,
+            dirs::home_dir().as_deref(),
+        )),
+        Source::Nvim | Source::Ghq => Ok(parse_lines(&fs::read_to_string(&file)?)),
+    }
+}
+
+/// Absolute paths of the repositories managed by ghq
+fn ghq_list() -> Result<Vec<PathBuf>, Error> {
+    let output = Command::new("ghq")
+        .args(["list", "--full-path"])
+        .output()
+        .map_err(|_| Error::InvalidPath(String::from("ghq is not installed")))?;
+
+    if !output.status.success() {
+        return Err(Error::InvalidPath(format!(
+            "ghq list failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(parse_lines(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// One path per line, as written by project.nvim and `ghq list --full-path`
+fn parse_lines(text: &str) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Paths of a projectile bookmarks file, a lisp list of strings like
+/// `("~/code/api/" "/srv/web/")`, remote TRAMP paths are skipped
+fn parse_projectile(text: &str, home: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+
+        let mut string = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => string.extend(chars.next()),
+                '"' => break,
+                c => string.push(c),
+            }
+        }
+
+        // `/ssh:host:/path` and similar
+        if string.starts_with('/') && string.split('/').nth(1).is_some_and(|s| s.contains(':')) {
+            continue;
+        }
+
+        let path = match (string.strip_prefix("~/"), home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(&string),
+        };
+        paths.push(path);
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_projectile_bookmarks() {
+        let text = r#"("~/code/api/" "/srv/my \"web\"/" "/ssh:box:/srv/remote/")"#;
+
+        assert_eq!(
+            parse_projectile(text, Some(Path::new("/home/alice"))),
+            vec![
+                PathBuf::from("/home/alice/code/api/"),
+                PathBuf::from("/srv/my \"web\"/"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_one_path_per_line() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("project_history");
+        fs::write(&file, "/srv/api\n\n  /srv/web  \n").unwrap();
+
+        assert_eq!(
+            read(Source::Nvim, Some(&file)).unwrap(),
+            vec![PathBuf::from("/srv/api"), PathBuf::from("/srv/web")]
+        );
+    }
+}
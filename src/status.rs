@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::git::{self, RepoStatus};
+use crate::utils;
+use crate::workspace::{Dir, Workspace};
+
+/// How `ws status` prints its result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format {}, use table or json", s)),
+        }
+    }
+}
+
+/// Status of one directory of a workspace
+#[derive(Debug, Serialize)]
+pub struct DirStatus {
+    pub workspace: String,
+    /// Label of the directory, or its path
+    pub dir: String,
+    pub path: PathBuf,
+    pub exists: bool,
+    /// `None` for directories which are not a repository
+    pub git: Option<RepoStatus>,
+}
+
+/// Status of all directories of `spaces`, the repositories are checked in parallel
+pub fn collect(spaces: &[Workspace]) -> Vec<DirStatus> {
+    let dirs: Vec<(&str, &Dir)> = spaces
+        .iter()
+        .flat_map(|ws| ws.dir_iter().map(move |dir| (ws.name.as_str(), dir)))
+        .collect();
+
+    utils::parallel_map(&dirs, utils::default_threads(), |(w_name, dir)| DirStatus {
+        workspace: String::from(*w_name),
+        dir: dir.display_name().into_owned(),
+        path: dir.path.clone(),
+        exists: dir.path.is_dir(),
+        git: git::status(&dir.path),
+    })
+}
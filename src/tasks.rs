@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use crate::workspace::{Dir, Workspace};
+
+/// Where a task is defined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    /// `[tasks]` of the project file of its directory
+    Project,
+    /// Added to the workspace with `ws tasks add`
+    Workspace,
+}
+
+/// A named command of a workspace like `build` or `test`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub name: String,
+    /// Directory the task runs in, `None` runs it in every directory
+    pub dir_id: Option<i32>,
+    pub command: String,
+    /// Tasks run first, `task` in the same directory or `dir:task`
+    pub depends: Vec<String>,
+    pub origin: Origin,
+}
+
+/// Dependencies as stored in the database
+pub fn join_depends(depends: &[String]) -> Option<String> {
+    (!depends.is_empty()).then(|| depends.join(","))
+}
+
+pub fn split_depends(text: Option<String>) -> Vec<String> {
+    text.iter()
+        .flat_map(|text| text.split(','))
+        .map(str::trim)
+        .filter(|dep| !dep.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// A task in one directory, what `ws run` executes
+#[derive(Debug)]
+pub struct Step<'a> {
+    pub dir: &'a Dir,
+    pub task: &'a Task,
+}
+
+impl Step<'_> {
+    /// `dir:task`
+    pub fn name(&self) -> String {
+        format!("{}:{}", self.dir.short_name(), self.task.name)
+    }
+}
+
+/// Task `name` of `dir`. Tasks added to the workspace take precedence over
+/// the ones of project files
+fn lookup<'a>(tasks: &'a [Task], dir: &Dir, name: &str) -> Option<&'a Task> {
+    let runs_in = |task: &&Task| task.name == name && task.dir_id.is_none_or(|id| id == dir.id);
+
+    tasks
+        .iter()
+        .filter(|task| task.origin == Origin::Workspace)
+        .find(runs_in)
+        .or_else(|| tasks.iter().find(runs_in))
+}
+
+/// Every step needed to run task `name` in the directories of `ws` having
+/// it, each step after the steps it depends on
+pub fn plan<'a>(ws: &'a Workspace, tasks: &'a [Task], name: &str) -> Result<Vec<Step<'a>>, String> {
+    let targets: Vec<Step> = ws
+        .dir_iter()
+        .filter_map(|dir| lookup(tasks, dir, name).map(|task| Step { dir, task }))
+        .collect();
+
+    if targets.is_empty() {
+        return Err(format!("Workspace {} has no task {}", ws.name, name));
+    }
+
+    let mut planner = Planner {
+        ws,
+        tasks,
+        visited: HashMap::new(),
+        steps: vec![],
+    };
+    for target in targets {
+        planner.visit(target, &mut vec![])?;
+    }
+
+    Ok(planner.steps)
+}
+
+struct Planner<'a> {
+    ws: &'a Workspace,
+    tasks: &'a [Task],
+    /// Steps by directory id and task name, `false` while their dependencies
+    /// are visited, `true` once planned
+    visited: HashMap<(i32, &'a str), bool>,
+    steps: Vec<Step<'a>>,
+}
+
+impl<'a> Planner<'a> {
+    fn visit(&mut self, step: Step<'a>, path: &mut Vec<String>) -> Result<(), String> {
+        let key = (step.dir.id, step.task.name.as_str());
+        path.push(step.name());
+
+        match self.visited.get(&key) {
+            Some(true) => {
+                path.pop();
+                return Ok(());
+            }
+            Some(false) => {
+                return Err(format!("Tasks depend on each other: {}", path.join(" -> ")))
+            }
+            None => {}
+        }
+        self.visited.insert(key, false);
+
+        for dep in &step.task.depends {
+            let dependency = self.resolve(step.dir, dep).ok_or_else(|| {
+                format!("{} depends on {}, which does not exist", step.name(), dep)
+            })?;
+            self.visit(dependency, path)?;
+        }
+
+        self.visited.insert(key, true);
+        self.steps.push(step);
+        path.pop();
+
+        Ok(())
+    }
+
+    /// Step `dep` of a task in `dir` refers to
+    fn resolve(&self, dir: &'a Dir, dep: &str) -> Option<Step<'a>> {
+        let (dir, name) = match dep.split_once(':') {
+            Some((other, name)) => {
+                let other = self
+                    .ws
+                    .dir_iter()
+                    .find(|dir| dir.short_name() == other)
+                    .or_else(|| self.ws.find_dir(other))?;
+                (other, name)
+            }
+            None => (dir, dep),
+        };
+
+        lookup(self.tasks, dir, name).map(|task| Step { dir, task })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(name: &str, dir_id: Option<i32>, depends: &[&str], origin: Origin) -> Task {
+        Task {
+            name: String::from(name),
+            dir_id,
+            command: format!("make {}", name),
+            depends: depends.iter().map(|dep| String::from(*dep)).collect(),
+            origin,
+        }
+    }
+
+    #[test]
+    fn plans_dependencies_first() {
+        let mut ws = Workspace::new(String::from("shop"));
+        ws.add_dir(Dir::new("/code/web").id(1));
+        ws.add_dir(Dir::new("/code/api").id(2));
+        ws.add_dir(Dir::new("/code/docs").id(3));
+
+        let mut tasks = vec![
+            task("build", Some(1), &["lint", "api:build"], Origin::Project),
+            task("build", Some(2), &["lint"], Origin::Project),
+            task("lint", None, &[], Origin::Workspace),
+            task("lint", Some(3), &[], Origin::Project),
+        ];
+
+        let names = |steps: Vec<Step>| -> Vec<String> { steps.iter().map(Step::name).collect() };
+        assert_eq!(
+            names(plan(&ws, &tasks, "build").unwrap()),
+            vec!["web:lint", "api:lint", "api:build", "web:build"]
+        );
+        // the workspace task wins over the project file of docs
+        assert_eq!(
+            plan(&ws, &tasks, "lint").unwrap()[2].task.origin,
+            Origin::Workspace
+        );
+        assert!(plan(&ws, &tasks, "deploy").is_err());
+
+        tasks.retain(|task| task.dir_id.is_some());
+        tasks.push(task("lint", Some(2), &["web:build"], Origin::Workspace));
+        tasks[0].depends = vec![String::from("api:build")];
+        let err = plan(&ws, &tasks, "build").unwrap_err();
+        assert_eq!(
+            err,
+            "Tasks depend on each other: web:build -> api:build -> api:lint -> web:build"
+        );
+
+        tasks[0].depends = vec![String::from("docs:test")];
+        assert!(plan(&ws, &tasks, "build")
+            .unwrap_err()
+            .contains("depends on docs:test"));
+    }
+}
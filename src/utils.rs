@@ -1,9 +1,408 @@
-use std::path::PathBuf;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
-pub fn get_canonical_path(path: PathBuf) -> String {
-    return std::fs::canonicalize(path)
-        .unwrap()
-        .to_str()
+use structopt::StructOpt;
+
+use crate::errors::Error;
+
+// How a path given on the command line becomes the stored directory, not a
+// doc comment as structopt would make it the about of the subcommands
+#[derive(StructOpt, Debug, Default, Clone, Copy)]
+pub struct PathOptions {
+    #[structopt(
+        long = "keep-symlinks",
+        help = "store symlinks instead of their target"
+    )]
+    pub keep_symlinks: bool,
+
+    #[structopt(long = "create", help = "create the directory when missing")]
+    pub create: bool,
+
+    #[structopt(
+        long = "allow-missing",
+        help = "register the directory even when it does not exist (yet)"
+    )]
+    pub allow_missing: bool,
+}
+
+/// Turns `path` into the absolute directory to store
+/// expanding `~` and environment variables first
+pub fn resolve_path(path: &Path, options: PathOptions) -> Result<PathBuf, Error> {
+    let expanded = expand_path(path.as_os_str())?;
+
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        env::current_dir()?.join(expanded)
+    };
+
+    if !absolute.exists() {
+        if options.create {
+            fs::create_dir_all(&absolute)?;
+        } else if options.allow_missing {
+            return Ok(normalize(&absolute));
+        } else {
+            return Err(Error::InvalidPath(format!(
+                "Directory {} does not exist, use --create or --allow-missing",
+                absolute.display()
+            )));
+        }
+    }
+
+    if !absolute.is_dir() {
+        return Err(Error::InvalidPath(format!(
+            "{} is not a directory",
+            absolute.display()
+        )));
+    }
+
+    if options.keep_symlinks {
+        Ok(normalize(&absolute))
+    } else {
+        Ok(fs::canonicalize(absolute)?)
+    }
+}
+
+/// Removes `.` and `..` from an absolute path without touching the file system
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Finds executable `binary` like the shell would, in `PATH` unless it is a path
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let binary = Path::new(binary);
+
+    if binary.components().count() > 1 {
+        return binary.is_file().then(|| binary.to_path_buf());
+    }
+
+    find_in_dirs(binary, env::split_paths(&env::var_os("PATH")?))
+}
+
+/// First of `dirs` containing `binary`, on Windows also with an executable
+/// extension appended
+fn find_in_dirs(binary: &Path, dirs: impl Iterator<Item = PathBuf>) -> Option<PathBuf> {
+    let extensions: &[&str] = if cfg!(windows) {
+        &["exe", "cmd", "bat"]
+    } else {
+        &[]
+    };
+
+    dirs.flat_map(|dir| {
+        let exact = dir.join(binary);
+        let appended = extensions.iter().map(move |ext| {
+            let mut name = binary.as_os_str().to_os_string();
+            name.push(format!(".{}", ext));
+            dir.join(name)
+        });
+
+        std::iter::once(exact).chain(appended)
+    })
+    .find(|candidate| candidate.is_file())
+}
+
+/// Moves `path` from below `old` to below `new`,
+/// `None` when `path` is not inside `old`
+pub fn replace_prefix(path: &Path, old: &Path, new: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(old).ok()?;
+
+    match rest.as_os_str().is_empty() {
+        true => Some(new.to_path_buf()),
+        false => Some(new.join(rest)),
+    }
+}
+
+/// Expands a leading `~` and `$VAR` / `${VAR}` from the environment
+pub fn expand_path(path: &OsStr) -> Result<PathBuf, Error> {
+    expand_path_with(path, &|name| {
+        if name == "HOME" {
+            dirs::home_dir().map(|p| p.into_os_string())
+        } else {
+            env::var_os(name)
+        }
+    })
+}
+
+fn expand_path_with(
+    path: &OsStr,
+    lookup: &dyn Fn(&str) -> Option<OsString>,
+) -> Result<PathBuf, Error> {
+    let bytes = os_str_to_bytes(path);
+    let mut expanded: Vec<u8> = vec![];
+    let mut i = 0;
+
+    if bytes.first() == Some(&b'~') && (bytes.len() == 1 || bytes[1] == b'/') {
+        let home = lookup("HOME")
+            .ok_or_else(|| Error::InvalidPath(String::from("Cannot find home directory")))?;
+        expanded.extend(os_str_to_bytes(&home));
+        i = 1;
+    }
+
+    while i < bytes.len() {
+        if bytes[i] != b'$' {
+            expanded.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let braced = bytes.get(i + 1) == Some(&b'{');
+        let start = if braced { i + 2 } else { i + 1 };
+        let end = start
+            + bytes[start..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                .count();
+
+        if end == start || (braced && bytes.get(end) != Some(&b'}')) {
+            // not a variable, keep the `$` as is
+            expanded.push(b'$');
+            i += 1;
+            continue;
+        }
+
+        let name = String::from_utf8_lossy(&bytes[start..end]);
+        let value = lookup(&name).ok_or_else(|| {
+            Error::InvalidPath(format!("Environment variable {} is not set", name))
+        })?;
+        expanded.extend(os_str_to_bytes(&value));
+
+        i = if braced { end + 1 } else { end };
+    }
+
+    Ok(bytes_to_path(expanded))
+}
+
+/// Applies `f` to all `items` on up to `threads` threads,
+/// the results are in the order of `items`
+pub fn parallel_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
         .unwrap()
-        .to_string();
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect()
+}
+
+/// Number of threads for work which mostly waits on other processes
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .max(4)
+}
+
+/// Directory ws keeps generated files in, created when missing
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| Error::InvalidPath(String::from("Cannot find cache directory")))?
+        .join("ws");
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir)
+}
+
+/// `name` with every character which is not safe in a file name replaced
+pub fn file_name_of(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn os_str_to_bytes(value: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    value.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn os_str_to_bytes(value: &OsStr) -> Vec<u8> {
+    value.to_string_lossy().into_owned().into_bytes()
+}
+
+/// Bytes a path is stored as in the database, lossless on unix
+pub fn path_to_bytes(path: &Path) -> Vec<u8> {
+    os_str_to_bytes(path.as_os_str())
+}
+
+/// Reverse of [`path_to_bytes`]
+#[cfg(unix)]
+pub fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    fn lookup(name: &str) -> Option<OsString> {
+        match name {
+            "HOME" => Some(OsString::from("/home/alice")),
+            "CODE" => Some(OsString::from("/mnt/code")),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_tilde_and_variables() {
+        let expand = |p: &str| expand_path_with(OsStr::new(p), &lookup).unwrap();
+
+        assert_eq!(expand("~"), PathBuf::from("/home/alice"));
+        assert_eq!(expand("~/api"), PathBuf::from("/home/alice/api"));
+        assert_eq!(expand("$CODE/api"), PathBuf::from("/mnt/code/api"));
+        assert_eq!(
+            expand("${CODE}_old/api"),
+            PathBuf::from("/mnt/code_old/api")
+        );
+        assert_eq!(expand("/a/~b/$/c"), PathBuf::from("/a/~b/$/c"));
+    }
+
+    #[test]
+    fn fails_on_unset_variable() {
+        assert!(expand_path_with(OsStr::new("$NOPE/api"), &lookup).is_err());
+    }
+
+    #[test]
+    fn normalizes_without_file_system() {
+        assert_eq!(normalize(Path::new("/a/./b/../c")), PathBuf::from("/a/c"));
+    }
+
+    #[test]
+    fn replaces_whole_components_only() {
+        let replace =
+            |p: &str| replace_prefix(Path::new(p), Path::new("/a/projects"), Path::new("/b/code"));
+
+        assert_eq!(
+            replace("/a/projects/api"),
+            Some(PathBuf::from("/b/code/api"))
+        );
+        assert_eq!(replace("/a/projects"), Some(PathBuf::from("/b/code")));
+        assert_eq!(replace("/a/projects2/api"), None);
+        assert_eq!(replace("/c/projects/api"), None);
+    }
+
+    #[test]
+    fn resolves_missing_dirs_only_when_asked() {
+        let root = tempfile::tempdir().unwrap();
+        let missing = root.path().join("missing");
+
+        assert!(resolve_path(&missing, PathOptions::default()).is_err());
+
+        let options = PathOptions {
+            allow_missing: true,
+            ..Default::default()
+        };
+        assert_eq!(resolve_path(&missing, options).unwrap(), missing);
+        assert!(!missing.exists());
+
+        let options = PathOptions {
+            create: true,
+            ..Default::default()
+        };
+        resolve_path(&missing, options).unwrap();
+        assert!(missing.is_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinks_when_asked() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("target");
+        let link = root.path().join("link");
+        std::fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let options = PathOptions {
+            keep_symlinks: true,
+            ..Default::default()
+        };
+        assert_eq!(resolve_path(&link, options).unwrap(), link);
+        assert_eq!(
+            resolve_path(&link, PathOptions::default()).unwrap(),
+            std::fs::canonicalize(&target).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn round_trips_non_utf8_paths() {
+        let path = bytes_to_path(b"/tmp/caf\xe9".to_vec());
+
+        assert_eq!(path_to_bytes(&path), b"/tmp/caf\xe9".to_vec());
+    }
+
+    #[test]
+    fn finds_binaries_with_dots_in_their_name() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("idea.sh"), "").unwrap();
+
+        let dirs = || vec![PathBuf::from("/nonexistent"), dir.path().to_path_buf()].into_iter();
+        assert_eq!(
+            find_in_dirs(Path::new("idea.sh"), dirs()),
+            Some(dir.path().join("idea.sh"))
+        );
+        assert_eq!(find_in_dirs(Path::new("idea"), dirs()), None);
+    }
+
+    #[test]
+    fn maps_in_parallel_keeping_the_order() {
+        let items: Vec<u32> = (0..50).collect();
+
+        assert_eq!(
+            parallel_map(&items, 8, |i| i * 2),
+            (0..100).step_by(2).collect::<Vec<_>>()
+        );
+        assert!(parallel_map(&[] as &[u32], 8, |i| *i).is_empty());
+    }
 }
@@ -0,0 +1,401 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::errors::Error;
+use crate::workspace::Workspace;
+
+/// Contents of a VS Code `.code-workspace` file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CodeWorkspace {
+    #[serde(default)]
+    pub folders: Vec<Folder>,
+    /// Everything else (`settings`, `extensions`, ...) is kept as is
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A root folder of a multi-root workspace, either a `path` or an `uri`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Folder {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+/// Checks if `editor` starts VS Code or one of its builds
+pub fn is_vscode(editor: &str) -> bool {
+    let binary = match editor.split_whitespace().next() {
+        Some(binary) => Path::new(binary),
+        None => return false,
+    };
+
+    matches!(
+        binary.file_stem().and_then(|stem| stem.to_str()),
+        Some("code" | "code-insiders" | "codium" | "vscodium")
+    )
+}
+
+/// Parses `text`, which like VS Code allows comments and trailing commas
+pub fn parse(text: &str) -> Result<CodeWorkspace, Error> {
+    serde_json::from_str(&strip_jsonc(text))
+        .map_err(|err| Error::InvalidFile(format!("Invalid workspace file: {}", err)))
+}
+
+/// Reads `file` and returns its folders as `(absolute path, name)`,
+/// relative paths are resolved against the directory of `file`
+pub fn read_folders(file: &Path) -> Result<Vec<(PathBuf, Option<String>)>, Error> {
+    let base = file.parent().unwrap_or(Path::new("."));
+    let code_workspace = parse(&fs::read_to_string(file)?)?;
+
+    let mut folders = vec![];
+    for folder in code_workspace.folders {
+        let path = match (&folder.path, &folder.uri) {
+            (Some(path), _) => base.join(path),
+            (None, Some(uri)) => match file_uri_to_path(uri) {
+                Some(path) => path,
+                None => {
+                    return Err(Error::InvalidFile(format!(
+                        "Folder {} is not on this machine",
+                        uri
+                    )))
+                }
+            },
+            (None, None) => continue,
+        };
+
+        folders.push((path, folder.name));
+    }
+
+    Ok(folders)
+}
+
+/// Builds the workspace file for `ws` to be written into directory `base`,
+/// directories inside `base` are stored relative to it like VS Code does
+pub fn from_workspace(
+    ws: &Workspace,
+    base: Option<&Path>,
+    other: Map<String, Value>,
+) -> CodeWorkspace {
+    let folders = ws
+        .dir_iter()
+        .map(|dir| {
+            let relative = base.and_then(|base| dir.path.strip_prefix(base).ok());
+            let path = match relative {
+                Some(rest) if rest.as_os_str().is_empty() => String::from("."),
+                Some(rest) => rest.to_string_lossy().into_owned(),
+                None => dir.path.to_string_lossy().into_owned(),
+            };
+
+            Folder {
+                name: dir.label.clone(),
+                path: Some(path),
+                uri: None,
+            }
+        })
+        .collect();
+
+    let mut other = other;
+    other
+        .entry("settings")
+        .or_insert_with(|| Value::Object(Map::new()));
+
+    CodeWorkspace { folders, other }
+}
+
+/// Writes `ws` to `file`, keeping the settings of an existing file
+pub fn write(ws: &Workspace, file: &Path) -> Result<(), Error> {
+    let other = match fs::read_to_string(file) {
+        Ok(text) => parse(&text)?.other,
+        Err(_) => Map::new(),
+    };
+
+    let base = file.parent().map(|p| match p.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => p,
+    });
+    let base = base.and_then(|base| fs::canonicalize(base).ok());
+
+    let code_workspace = from_workspace(ws, base.as_deref(), other);
+    fs::write(file, to_string(&code_workspace) + "\n")?;
+
+    Ok(())
+}
+
+/// Formats `code_workspace` the way VS Code writes it
+pub fn to_string(code_workspace: &CodeWorkspace) -> String {
+    // a struct of strings and json values always serializes
+    serde_json::to_string_pretty(code_workspace).unwrap()
+}
+
+/// Key of the recently opened folders in VS Code's `state.vscdb`
+const RECENT_KEY: &str = "history.recentlyOpenedPathsList";
+
+/// Files VS Code keeps its recently opened folders in, newest format first
+pub fn recent_files() -> Vec<PathBuf> {
+    let Some(config) = dirs::config_dir() else {
+        return vec![];
+    };
+
+    ["Code", "Code - Insiders", "VSCodium"]
+        .iter()
+        .flat_map(|app| {
+            let user = config.join(app).join("User").join("globalStorage");
+            [
+                user.join("state.vscdb"),
+                user.join("storage.json"),
+                config.join(app).join("storage.json"),
+            ]
+        })
+        .collect()
+}
+
+/// Recently opened local folders stored in `file`, either the `state.vscdb`
+/// database of current versions or the `storage.json` of older ones
+pub fn recent_folders(file: &Path) -> Result<Vec<PathBuf>, Error> {
+    let invalid =
+        |err: String| Error::InvalidFile(format!("Cannot read {}: {}", file.display(), err));
+
+    let json = match file.extension().and_then(|ext| ext.to_str()) {
+        Some("vscdb") => {
+            let conn = Connection::open_with_flags(file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(|err| invalid(err.to_string()))?;
+            conn.query_row(
+                "SELECT CAST(value AS TEXT) FROM ItemTable WHERE key = ?1",
+                [RECENT_KEY],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|err| invalid(err.to_string()))?
+        }
+        _ => fs::read_to_string(file)?,
+    };
+
+    let value: Value = serde_json::from_str(&json).map_err(|err| invalid(err.to_string()))?;
+    Ok(parse_recent(&value))
+}
+
+/// Folders of a recently opened list, `{"entries": [{"folderUri": ..}]}`
+/// or the `openedPathsList` of `storage.json`, remote folders are skipped
+fn parse_recent(value: &Value) -> Vec<PathBuf> {
+    let list = value.get("openedPathsList").unwrap_or(value);
+
+    let entries = ["entries", "workspaces3", "workspaces2"]
+        .iter()
+        .filter_map(|key| list.get(key).and_then(|v| v.as_array()))
+        .flatten();
+
+    let mut folders: Vec<PathBuf> = vec![];
+    for entry in entries {
+        let uri = match entry {
+            Value::String(uri) => Some(uri.as_str()),
+            entry => entry.get("folderUri").and_then(|uri| uri.as_str()),
+        };
+
+        if let Some(path) = uri.and_then(file_uri_to_path) {
+            if !folders.contains(&path) {
+                folders.push(path);
+            }
+        }
+    }
+
+    folders
+}
+
+/// Removes `//` and `/* */` comments and trailing commas outside of strings
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            (']' | '}', _) => {
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Path of a `file://` uri, `None` for other schemes
+fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // `file:///C:/code` on windows
+    let rest = match rest.as_bytes() {
+        [b'/', _, b':', ..] if cfg!(windows) => &rest[1..],
+        _ => rest,
+    };
+
+    let bytes = rest.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    Some(crate::utils::bytes_to_path(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::workspace::Dir;
+
+    #[test]
+    fn reads_folders_relative_to_the_file() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("team.code-workspace");
+        fs::write(
+            &file,
+            r#"{
+                // the api
+                "folders": [
+                    { "path": "api", "name": "backend" },
+                    { "path": "/srv/web" }, /* absolute */
+                    { "uri": "file:///srv/my%20docs" },
+                ],
+                "settings": { "url": "http://localhost//" },
+            }"#,
+        )
+        .unwrap();
+
+        let folders = read_folders(&file).unwrap();
+
+        assert_eq!(
+            folders,
+            vec![
+                (root.path().join("api"), Some(String::from("backend"))),
+                (PathBuf::from("/srv/web"), None),
+                (PathBuf::from("/srv/my docs"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_labels_and_keeps_settings() {
+        let root = tempfile::tempdir().unwrap();
+        let base = fs::canonicalize(root.path()).unwrap();
+        let file = base.join("team.code-workspace");
+        fs::write(&file, r#"{ "folders": [], "settings": { "a": 1 } }"#).unwrap();
+
+        let mut ws = Workspace::new(String::from("team"));
+        let mut api = Dir::new(base.join("api"));
+        api.label = Some(String::from("api"));
+        ws.add_dir(api);
+        ws.add_dir(Dir::new("/srv/web"));
+
+        write(&ws, &file).unwrap();
+        let written = parse(&fs::read_to_string(&file).unwrap()).unwrap();
+
+        assert_eq!(written.folders[0].name.as_deref(), Some("api"));
+        assert_eq!(written.folders[0].path.as_deref(), Some("api"));
+        assert_eq!(written.folders[1].name, None);
+        assert_eq!(written.folders[1].path.as_deref(), Some("/srv/web"));
+        assert_eq!(written.other["settings"]["a"], 1);
+        assert_eq!(read_folders(&file).unwrap()[0].0, base.join("api"));
+    }
+
+    #[test]
+    fn reads_recent_folders_from_the_state_database() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("state.vscdb");
+        let conn = Connection::open(&file).unwrap();
+        conn.execute_batch("CREATE TABLE ItemTable (key TEXT UNIQUE, value BLOB)")
+            .unwrap();
+        conn.execute(
+            "INSERT INTO ItemTable (key, value) VALUES (?1, ?2)",
+            [
+                RECENT_KEY,
+                r#"{"entries": [
+                    { "folderUri": "file:///srv/api" },
+                    { "fileUri": "file:///srv/notes.md" },
+                    { "folderUri": "vscode-remote://ssh-remote+box/srv/web" },
+                    { "workspace": { "configPath": "file:///srv/team.code-workspace" } },
+                    { "folderUri": "file:///srv/api" }
+                ]}"#,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            recent_folders(&file).unwrap(),
+            vec![PathBuf::from("/srv/api")]
+        );
+
+        let storage = root.path().join("storage.json");
+        fs::write(
+            &storage,
+            r#"{ "openedPathsList": { "workspaces3": ["file:///srv/web", { "configURIPath": "x" }] } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            recent_folders(&storage).unwrap(),
+            vec![PathBuf::from("/srv/web")]
+        );
+    }
+
+    #[test]
+    fn recognizes_vscode_editors() {
+        assert!(is_vscode("code"));
+        assert!(is_vscode("/usr/bin/codium --new-window"));
+        assert!(!is_vscode("vim"));
+        assert!(!is_vscode(""));
+    }
+}
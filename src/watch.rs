@@ -0,0 +1,196 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+/// Decides which of the watched directories a changed path belongs to,
+/// leaving out what `.gitignore` files exclude
+pub struct Roots {
+    roots: Vec<PathBuf>,
+    /// Matchers of the `.gitignore` files read so far by their directory,
+    /// `None` when the directory has none
+    ignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl Roots {
+    pub fn new(roots: Vec<PathBuf>) -> Roots {
+        Roots {
+            roots,
+            ignores: HashMap::new(),
+        }
+    }
+
+    /// Index of the root `path` is in, `None` when it is in none of them or
+    /// ignored. Of nested roots the innermost wins
+    pub fn root_of(&mut self, path: &Path) -> Option<usize> {
+        let (index, root) = self
+            .roots
+            .iter()
+            .enumerate()
+            .filter(|(_, root)| path.starts_with(root))
+            .max_by_key(|(_, root)| root.components().count())?;
+        let root = root.clone();
+
+        match self.is_ignored(&root, path) {
+            true => None,
+            false => Some(index),
+        }
+    }
+
+    fn is_ignored(&mut self, root: &Path, path: &Path) -> bool {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        if relative
+            .components()
+            .any(|component| component.as_os_str() == ".git")
+        {
+            return true;
+        }
+
+        // a deleted path can not tell whether it was a directory
+        let is_dir = path.is_dir();
+        let mut ignored = false;
+        // `.gitignore` files further down override the ones above
+        for dir in relative
+            .ancestors()
+            .skip(1)
+            .collect::<Vec<_>>()
+            .iter()
+            .rev()
+        {
+            let dir = match dir.as_os_str().is_empty() {
+                true => root.to_path_buf(),
+                false => root.join(dir),
+            };
+            let Some(matcher) = self.matcher(&dir) else {
+                continue;
+            };
+
+            let matched = matcher.matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                ignored = true;
+            } else if matched.is_whitelist() {
+                ignored = false;
+            }
+        }
+
+        ignored
+    }
+
+    fn matcher(&mut self, dir: &Path) -> Option<&Gitignore> {
+        self.ignores
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let file = dir.join(".gitignore");
+                if !file.is_file() {
+                    return None;
+                }
+
+                let mut builder = GitignoreBuilder::new(dir);
+                builder.add(file);
+                builder.build().ok()
+            })
+            .as_ref()
+    }
+
+    /// Forgets the matcher of a `.gitignore` file which changed
+    fn changed(&mut self, path: &Path) {
+        if path.file_name().is_some_and(|name| name == ".gitignore") {
+            if let Some(dir) = path.parent() {
+                self.ignores.remove(dir);
+            }
+        }
+    }
+}
+
+/// Watches `roots` recursively and calls `on_change` with the indexes of the
+/// roots in which files changed, once no further change followed for
+/// `debounce`. Returns only when watching fails
+pub fn watch<F>(roots: &[PathBuf], debounce: Duration, mut on_change: F) -> Result<(), String>
+where
+    F: FnMut(&BTreeSet<usize>),
+{
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(|err| err.to_string())?;
+    for root in roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|err| format!("Cannot watch {}: {}", root.display(), err))?;
+    }
+
+    let mut matcher = Roots::new(roots.to_vec());
+    let mut changed = BTreeSet::new();
+
+    loop {
+        let received = match changed.is_empty() {
+            true => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            false => receiver.recv_timeout(debounce),
+        };
+
+        match received {
+            Ok(Ok(event)) => changed.extend(affected(&mut matcher, &event)),
+            Ok(Err(err)) => return Err(err.to_string()),
+            Err(RecvTimeoutError::Timeout) => {
+                on_change(&changed);
+                // changes made while the command ran wait in the channel and
+                // run it again, its output is usually ignored by `.gitignore`
+                changed.clear();
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(String::from("The file watcher stopped"))
+            }
+        }
+    }
+}
+
+/// Roots in which `event` changed a file which is not ignored
+fn affected(matcher: &mut Roots, event: &Event) -> Vec<usize> {
+    // reading files, e.g. by the command itself, changes nothing
+    if matches!(event.kind, EventKind::Access(_)) {
+        return vec![];
+    }
+
+    event
+        .paths
+        .iter()
+        .filter_map(|path| {
+            matcher.changed(path);
+            matcher.root_of(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn maps_changes_to_roots_and_skips_ignored_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (api, docs) = (dir.path().join("api"), dir.path().join("api/docs"));
+        fs::create_dir_all(api.join("target")).unwrap();
+        fs::create_dir_all(api.join("logs")).unwrap();
+        fs::create_dir_all(docs.join("build")).unwrap();
+        fs::write(api.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(api.join("logs/.gitignore"), "!keep.log\n").unwrap();
+        fs::write(docs.join(".gitignore"), "build/\n").unwrap();
+
+        let mut roots = Roots::new(vec![api.clone(), docs.clone(), dir.path().join("web")]);
+
+        assert_eq!(roots.root_of(&api.join("src/main.rs")), Some(0));
+        assert_eq!(roots.root_of(&docs.join("index.md")), Some(1));
+        assert_eq!(roots.root_of(&dir.path().join("web/app.js")), Some(2));
+        assert_eq!(roots.root_of(&dir.path().join("other/app.js")), None);
+
+        assert_eq!(roots.root_of(&api.join("target/debug/ws")), None);
+        assert_eq!(roots.root_of(&api.join("server.log")), None);
+        assert_eq!(roots.root_of(&api.join(".git/index")), None);
+        assert_eq!(roots.root_of(&docs.join("build/index.html")), None);
+        // `.gitignore` files further down take precedence
+        assert_eq!(roots.root_of(&api.join("logs/keep.log")), Some(0));
+        assert_eq!(roots.root_of(&api.join("logs/other.log")), None);
+    }
+}
@@ -1,6 +1,15 @@
 use crate::db;
+use crate::errors::Error;
+use crate::git::GitIdentity;
+use crate::utils;
+use crate::vscode;
 use colored::*;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+#[allow(clippy::legacy_numeric_constants, clippy::useless_attribute)]
 use std::{process::Command, usize, vec};
 
 #[derive(Debug)]
@@ -8,7 +17,17 @@ pub struct Dir {
     pub id: i32,
     pub init: Option<String>, // init is for a script which will run on this path (currently
     // unavailable)
-    pub path: String,
+    pub path: PathBuf,
+    /// Short name to reference the directory with, e.g. `api`
+    pub label: Option<String>,
+    /// Directory used by commands which need a single one
+    pub primary: bool,
+    /// Identity of the git repository last seen in the directory
+    pub git: Option<GitIdentity>,
+    /// Text of the project file last applied to the directory
+    pub project: Option<String>,
+    /// Scripts of the project file the user trusted to run
+    pub trusted_scripts: Option<String>,
 }
 
 #[cfg(target_os = "windows")]
@@ -24,16 +43,44 @@ pub struct Workspace {
     dirs: Vec<Dir>,
     /// Name of the workspace
     pub name: String,
+    /// Editor used for this workspace instead of the default one
+    pub editor: Option<String>,
+    /// Environment variables set for commands run in the workspace
+    pub env: BTreeMap<String, String>,
 }
 
 impl Dir {
-    pub fn new(path: String) -> Dir {
+    #[allow(dead_code)]
+    pub fn new<P: Into<PathBuf>>(path: P) -> Dir {
         Dir {
-            path,
+            path: path.into(),
             id: 0,
             init: None,
+            label: None,
+            primary: false,
+            git: None,
+            project: None,
+            trusted_scripts: None,
+        }
+    }
+
+    /// Label of the directory, or its path when unlabeled
+    pub fn display_name(&self) -> Cow<'_, str> {
+        match &self.label {
+            Some(label) => Cow::Borrowed(label),
+            None => self.path.to_string_lossy(),
+        }
+    }
+
+    /// Label of the directory, or the last component of its path
+    pub fn short_name(&self) -> Cow<'_, str> {
+        match (&self.label, self.path.file_name()) {
+            (Some(label), _) => Cow::Borrowed(label),
+            (None, Some(name)) => name.to_string_lossy(),
+            (None, None) => self.path.to_string_lossy(),
         }
     }
+
     #[allow(dead_code)]
     pub fn id(mut self, id: i32) -> Dir {
         self.id = id;
@@ -41,6 +88,7 @@ impl Dir {
     }
 
     #[allow(dead_code)]
+    #[allow(clippy::needless_lifetimes)]
     pub fn init<'a>(&'a mut self, script: String) -> &'a mut Self {
         self.init = Some(script);
 
@@ -59,6 +107,12 @@ impl Workspace {
         self.id
     }
 
+    /// Name of files kept for the workspace, unique even when sanitizing
+    /// makes names like `a.b` and `a_b` the same
+    pub fn file_name(&self) -> String {
+        format!("{}-{}", utils::file_name_of(&self.name), self.id)
+    }
+
     pub fn set_id(&mut self, id: i32) {
         if self.id == 0 {
             self.id = id;
@@ -70,14 +124,38 @@ impl Workspace {
     /// Checks if a directory exists in a workspace
     /// Returns position [`Option<usize>`] of the directory is exists
     /// Else return [`None`]
-    pub fn check_dir_already_exists(&self, dir: &str) -> Option<usize> {
-        self.dirs.iter().position(|x| x.path == dir)
+    pub fn check_dir_already_exists<P: AsRef<Path>>(&self, dir: P) -> Option<usize> {
+        self.dirs.iter().position(|x| x.path == dir.as_ref())
     }
 
+    /// Finds a directory of the workspace by its label or path
+    pub fn find_dir(&self, dir: &str) -> Option<&Dir> {
+        self.dirs
+            .iter()
+            .find(|x| x.label.as_deref() == Some(dir))
+            .or_else(|| self.check_dir_already_exists(dir).map(|i| &self.dirs[i]))
+    }
+
+    /// Directory flagged as primary, else the first one
+    pub fn primary_dir(&self) -> Option<&Dir> {
+        self.dirs.iter().find(|x| x.primary).or(self.dirs.first())
+    }
+
+    #[allow(mismatched_lifetime_syntaxes)]
     pub fn dir_iter(&self) -> std::slice::Iter<Dir> {
         self.dirs.iter()
     }
 
+    pub fn dirs_mut(&mut self) -> std::slice::IterMut<'_, Dir> {
+        self.dirs.iter_mut()
+    }
+
+    /// Keeps only the directories matching `f`
+    pub fn retain_dirs<F: FnMut(&Dir) -> bool>(&mut self, f: F) {
+        self.dirs.retain(f)
+    }
+
+    #[allow(dead_code)]
     pub fn remove_dir(&mut self, dir: &str) {
         let value = self.check_dir_already_exists(dir);
 
@@ -105,35 +183,155 @@ impl Workspace {
             dirs: vec![],
             name,
             id: 0,
+            editor: None,
+            env: BTreeMap::new(),
         }
     }
 
+    #[allow(dead_code)]
     pub fn id(self, id: i32) -> Self {
         Workspace {
             id,
             dirs: self.dirs,
             name: self.name,
+            editor: self.editor,
+            env: self.env,
         }
     }
 }
 
+/// Interactive shell of the user
+pub fn user_shell() -> String {
+    env::var("SHELL").unwrap_or(String::from(SHELL))
+}
+
+/// Shell editor commands are run with
+pub fn command_shell() -> &'static str {
+    SHELL
+}
+
+/// Starts the user's shell inside `dir` and waits for it to exit
+pub fn open_shell(dir: &Dir) -> std::io::Result<()> {
+    let shell = user_shell();
+
+    println!("Opening {} in {}", shell, dir.path.display());
+
+    Command::new(shell).current_dir(&dir.path).status()?;
+
+    Ok(())
+}
+
+/// Runs `script` with the command shell inside `dir` and waits for it
+pub fn run_script(
+    dir: &Dir,
+    script: &str,
+    env: &BTreeMap<String, String>,
+) -> std::io::Result<ExitStatus> {
+    script_command(&dir.path, script, env).status()
+}
+
+/// Command running `script` with the command shell inside `dir`
+pub fn script_command(dir: &Path, script: &str, env: &BTreeMap<String, String>) -> Command {
+    let flag = if cfg!(windows) { "-Command" } else { "-c" };
+
+    let mut command = Command::new(SHELL);
+    command.args([flag, script]).current_dir(dir).envs(env);
+
+    command
+}
+
+/// Script for the command shell running the command given on the command
+/// line, a single argument is already a script and the arguments of a
+/// program are quoted to keep them apart
+pub fn script_of(command: &[String]) -> String {
+    match command {
+        [script] => script.clone(),
+        args => args
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-+=@%:,./".contains(c));
+
+    match plain {
+        true => arg.to_string(),
+        false if cfg!(windows) => format!("'{}'", arg.replace('\'', "''")),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+/// Opens `file` in `$VISUAL` or `$EDITOR` and waits until the editor exits
+pub fn open_in_editor(file: &Path) -> std::io::Result<ExitStatus> {
+    let fallback = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or(String::from(fallback));
+
+    if cfg!(windows) {
+        let command = format!("{} \"{}\"", editor, file.display());
+        return Command::new(SHELL).args(["-Command", &command]).status();
+    }
+
+    Command::new(SHELL)
+        .args(["-c", &format!("{} \"$1\"", editor), "ws"])
+        .arg(file)
+        .status()
+}
+
 pub fn open_workspace(workspace: Workspace) {
-    let editor = db::get_editor().unwrap();
+    let editor = match &workspace.editor {
+        Some(editor) => editor.clone(),
+        None => db::get_editor().unwrap(),
+    };
     println!("Opening workspace using editor {}", editor);
 
     let os = env::consts::OS;
 
-    workspace.dir_iter().for_each(|d| {
-        let formatted_cmd = format!("{} \"{}\"", editor, d.path);
-        let args = match os {
-            "windows" => vec!["-Command", formatted_cmd.as_str()],
-            _ => vec!["-c", formatted_cmd.as_str()],
+    // VS Code opens all directories in one window from a workspace file
+    let targets: Vec<PathBuf> = match vscode::is_vscode(&editor) && workspace.dir_iter().count() > 1
+    {
+        true => match vscode_workspace_file(&workspace) {
+            Ok(file) => vec![file],
+            Err(err) => {
+                println!(
+                    "{}",
+                    format!("Cannot write workspace file: {}", err).yellow()
+                );
+                workspace.dir_iter().map(|d| d.path.clone()).collect()
+            }
+        },
+        false => workspace.dir_iter().map(|d| d.path.clone()).collect(),
+    };
+
+    targets.iter().for_each(|path| {
+        let cmd = match os {
+            "windows" => {
+                let formatted_cmd = format!("{} \"{}\"", editor, path.display());
+                Command::new(SHELL)
+                    .args(vec!["-Command", formatted_cmd.as_str()])
+                    .spawn()
+            }
+            // the path is passed as `$1` so it reaches the editor byte for byte
+            _ => Command::new(SHELL)
+                .args(vec!["-c", &format!("{} \"$1\"", editor), "ws"])
+                .arg(path)
+                .spawn(),
         };
-        let cmd = Command::new(SHELL).args(args).spawn();
 
         match cmd {
             Ok(child) => {
-                print!("Editor Instance Spawned for {} :> {}", d.path, child.id());
+                print!(
+                    "Editor Instance Spawned for {} :> {}",
+                    path.display(),
+                    child.id()
+                );
             }
             Err(e) => {
                 println!("{}", e);
@@ -142,11 +340,23 @@ pub fn open_workspace(workspace: Workspace) {
     });
 }
 
+/// Writes the `.code-workspace` file `ws open` hands to VS Code,
+/// settings changed in the window are kept between runs
+fn vscode_workspace_file(workspace: &Workspace) -> Result<PathBuf, Error> {
+    let file = utils::cache_dir()?.join(format!("{}.code-workspace", workspace.file_name()));
+
+    vscode::write(workspace, &file)?;
+
+    Ok(file)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use crate::db;
 
-    use super::Workspace;
+    use super::{script_of, Workspace};
 
     use super::Dir;
     #[test]
@@ -164,6 +374,33 @@ mod tests {
         w
     }
 
+    #[test]
+    fn file_names_differ_for_similar_names() {
+        let mut dotted = Workspace::new(String::from("a.b"));
+        dotted.set_id(1);
+        let mut underscored = Workspace::new(String::from("a_b"));
+        underscored.set_id(2);
+
+        assert_eq!(dotted.file_name(), "a_b-1");
+        assert_ne!(dotted.file_name(), underscored.file_name());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quotes_the_arguments_of_programs() {
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+
+        assert_eq!(
+            script_of(&args(&["make && make test"])),
+            "make && make test"
+        );
+        assert_eq!(script_of(&args(&["sh", "-c", "a && b"])), "sh -c 'a && b'");
+        assert_eq!(
+            script_of(&args(&["echo", "it's $HOME;", ""])),
+            "echo 'it'\\''s $HOME;' ''"
+        );
+    }
+
     #[test]
     fn test_get_editor() {
         let editor = db::get_editor().unwrap();
@@ -188,4 +425,32 @@ mod tests {
 
         assert_eq!(w.dirs.len(), 2);
     }
+
+    #[test]
+    fn find_dir_by_label_or_path() {
+        let mut w = create_sample_workspace();
+        let mut dir = Dir::new("/code/api");
+        dir.label = Some("api".to_string());
+        w.add_dir(dir);
+
+        assert_eq!(w.find_dir("api").unwrap().path, Path::new("/code/api"));
+        assert_eq!(
+            w.find_dir("/code/api").unwrap().path,
+            Path::new("/code/api")
+        );
+        assert!(w.find_dir("web").is_none());
+    }
+
+    #[test]
+    fn primary_dir_defaults_to_first() {
+        let mut w = create_sample_workspace();
+
+        assert_eq!(w.primary_dir().unwrap().path, Path::new("Marcus"));
+
+        let mut dir = Dir::new("Primary");
+        dir.primary = true;
+        w.add_dir(dir);
+
+        assert_eq!(w.primary_dir().unwrap().path, Path::new("Primary"));
+    }
 }